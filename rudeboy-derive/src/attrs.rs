@@ -0,0 +1,96 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote_spanned;
+use syn::spanned::Spanned;
+
+/// A single entry of a `#[lua(...)]` helper attribute: either a bare flag, such
+/// as `skip`, or a name with a string value, such as `overload = "move"`
+struct LuaAttr {
+    name: syn::Ident,
+    value: Option<syn::LitStr>,
+}
+
+/// The entries of every `#[lua(...)]` helper attribute on an item
+pub(crate) struct LuaAttrs {
+    attrs: Vec<LuaAttr>,
+}
+
+impl LuaAttrs {
+    const IDENT: &'static str = "lua";
+
+    /// Collects the `#[lua(...)]` attributes from the given list, checking
+    /// that each entry is one of `flags` or `values`
+    pub(crate) fn parse(
+        attrs: &[syn::Attribute],
+        flags: &[&str],
+        values: &[&str],
+    ) -> Result<LuaAttrs, TokenStream2> {
+        let mut ret = Vec::new();
+        for attr in attrs.iter().filter(|a| a.path.is_ident(Self::IDENT)) {
+            let list = match attr.parse_meta() {
+                Ok(syn::Meta::List(list)) => list,
+                Ok(meta) => {
+                    return Err(quote_spanned! {
+                        meta.span() => compile_error!("Expected a list of the form #[lua(...)]");
+                    })
+                }
+                Err(e) => return Err(e.to_compile_error()),
+            };
+
+            for nested in &list.nested {
+                use syn::{Lit, Meta, NestedMeta};
+                let (path, value) = match nested {
+                    NestedMeta::Meta(Meta::Path(p)) => (p, None),
+                    NestedMeta::Meta(Meta::NameValue(syn::MetaNameValue {
+                        path,
+                        lit: Lit::Str(s),
+                        ..
+                    })) => (path, Some(s.clone())),
+                    _ => {
+                        return Err(quote_spanned! {
+                            nested.span() => compile_error!("Expected a lua attribute of the form `name` or `name = \"value\"`");
+                        })
+                    }
+                };
+
+                let name = match path.get_ident() {
+                    Some(name) => name.clone(),
+                    None => {
+                        return Err(quote_spanned! {
+                            path.span() => compile_error!("Expected a lua attribute name");
+                        })
+                    }
+                };
+
+                let allowed = if value.is_some() { values } else { flags };
+                if !allowed.iter().any(|a| name == a) {
+                    return Err(quote_spanned! {
+                        nested.span() => compile_error!("Unexpected lua attribute");
+                    });
+                }
+
+                ret.push(LuaAttr { name, value });
+            }
+        }
+
+        Ok(LuaAttrs { attrs: ret })
+    }
+
+    /// Returns true if the given flag was set
+    pub(crate) fn has(&self, flag: &str) -> bool {
+        self.attrs.iter().any(|a| a.value.is_none() && a.name == flag)
+    }
+
+    /// Returns the value given for the given name, if any
+    pub(crate) fn value(&self, name: &str) -> Option<&syn::LitStr> {
+        self.attrs
+            .iter()
+            .filter(|a| a.name == name)
+            .find_map(|a| a.value.as_ref())
+    }
+
+    /// Removes every `#[lua(...)]` attribute from the given list, so that the
+    /// item can be emitted without them
+    pub(crate) fn strip(attrs: &mut Vec<syn::Attribute>) {
+        attrs.retain(|a| !a.path.is_ident(Self::IDENT));
+    }
+}
@@ -0,0 +1,86 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+/// Builds the expression constructing the given variant from its lua
+/// arguments, which are in `args`
+fn construct(e: &syn::ItemEnum, variant: &syn::Variant) -> TokenStream2 {
+    let name = &variant.ident;
+    let constructor = format!("{}.{}", e.ident, name);
+    match &variant.fields {
+        syn::Fields::Unit => quote!(Self::#name),
+        syn::Fields::Unnamed(fields) => {
+            let count = fields.unnamed.len();
+            let values = fields.unnamed.iter().enumerate().map(|(i, field)| {
+                let ty = &field.ty;
+                let what = format!("argument #{}", i + 1);
+                quote! {
+                    ::rudeboy::__private::constructor_arg::<#ty>(ctx, #constructor, #what, args.next().unwrap())?
+                }
+            });
+            quote! {{
+                let mut args = ::rudeboy::__private::constructor_args(#constructor, #count, args)?.into_iter();
+                Self::#name(#( #values ),*)
+            }}
+        }
+        syn::Fields::Named(fields) => {
+            let members: Vec<_> = fields.named.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+            let keys = members.iter().map(|m| m.to_string());
+            let values = fields.named.iter().map(|field| {
+                let ty = &field.ty;
+                let what = format!("field '{}'", field.ident.as_ref().unwrap());
+                quote! {
+                    ::rudeboy::__private::constructor_arg::<#ty>(ctx, #constructor, #what, fields.next().unwrap())?
+                }
+            });
+            quote! {{
+                let mut fields = ::rudeboy::__private::constructor_fields(
+                    #constructor,
+                    &[#( #keys ),*],
+                    args,
+                )?.into_iter();
+                Self::#name { #( #members: #values ),* }
+            }}
+        }
+    }
+}
+
+/// Generates a `RudeboyTable` implementation for an enum, giving a table of
+/// its variants by name. Variants with fields are constructed by calling them,
+/// with positional arguments for tuple variants, or a table of fields for
+/// struct variants. Variants without fields are created anew each time they
+/// are looked up, so that changes to one value don't affect the others.
+pub(crate) fn constructors(e: &syn::ItemEnum) -> TokenStream2 {
+    let (units, calls): (Vec<_>, Vec<_>) = e.variants.iter().partition(|v| v.fields.is_empty());
+
+    let unit_names = units.iter().map(|v| v.ident.to_string());
+    let unit_values = units.iter().map(|v| construct(e, v));
+    let call_names = calls.iter().map(|v| v.ident.to_string());
+    let call_values = calls.iter().map(|v| construct(e, v));
+
+    let name = &e.ident;
+    let type_name = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = e.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::rudeboy::RudeboyTable for #name #ty_generics #where_clause {
+            fn generate_table<'lua>(ctx: ::rlua::Context<'lua>) -> ::rlua::Result<::rlua::Table<'lua>> {
+                let table = ctx.create_table()?;
+                #(
+                    table.set(#call_names, ctx.create_function(|ctx, args: ::rlua::MultiValue<'lua>| {
+                        Ok(#call_values)
+                    })?)?;
+                )*
+
+                let metatable = ctx.create_table()?;
+                metatable.set("__index", ctx.create_function(|ctx, (_, key): (::rlua::Table, ::rlua::Value)| {
+                    let key = <::rlua::String as ::rlua::FromLua>::from_lua(key, ctx)?;
+                    match key.to_str()? {
+                        #( #unit_names => ::rlua::ToLua::to_lua(#unit_values, ctx), )*
+                        key => Err(::rudeboy::__private::no_variant(#type_name, key)),
+                    }
+                })?)?;
+                table.set_metatable(Some(metatable));
+                Ok(table)
+            }
+        }
+    }
+}
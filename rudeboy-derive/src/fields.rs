@@ -0,0 +1,170 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+
+use crate::attrs::LuaAttrs;
+
+/// How a field is handed to lua when it is read
+#[derive(PartialEq)]
+pub(crate) enum FieldKind {
+    /// A copy of the field is converted to a lua value
+    Value,
+    /// A `rudeboy::MapProxy` for the field is returned
+    MapProxy,
+    /// A `rudeboy::FieldProxy` for the field is returned
+    Proxy,
+}
+
+/// A field of a struct or enum variant that is exposed to lua
+pub(crate) struct FieldInfo {
+    /// The field's name, or its position for tuple fields
+    pub member: syn::Member,
+    /// The name the field is exposed under in lua, for named fields
+    pub lua_name: Option<String>,
+    pub ty: syn::Type,
+    pub kind: FieldKind,
+}
+
+impl FieldInfo {
+    const FLAGS: &'static [&'static str] = &["skip", "sequence", "map_proxy", "proxy"];
+    const VALUES: &'static [&'static str] = &["rename"];
+
+    /// Parses the fields that are exposed to lua, leaving out those marked to
+    /// be skipped
+    pub(crate) fn parse_all(fields: &syn::Fields) -> Result<Vec<Self>, TokenStream2> {
+        let mut ret = Vec::new();
+        for (i, field) in fields.iter().enumerate() {
+            let attrs = LuaAttrs::parse(&field.attrs, Self::FLAGS, Self::VALUES)?;
+            if attrs.has("skip") {
+                continue;
+            }
+
+            let (member, lua_name) = match &field.ident {
+                Some(ident) => {
+                    let lua_name = match attrs.value("rename") {
+                        Some(rename) => rename.value(),
+                        None => ident.to_string(),
+                    };
+                    (syn::Member::Named(ident.clone()), Some(lua_name))
+                }
+                None => {
+                    if let Some(rename) = attrs.value("rename") {
+                        return Err(quote_spanned! {
+                            rename.span() => compile_error!("Only named fields can be renamed");
+                        });
+                    }
+                    (syn::Member::Unnamed(i.into()), None)
+                }
+            };
+
+            let kind = match (attrs.has("map_proxy"), attrs.has("proxy")) {
+                (false, false) => FieldKind::Value,
+                (true, false) => FieldKind::MapProxy,
+                (false, true) => FieldKind::Proxy,
+                (true, true) => {
+                    return Err(quote_spanned! {
+                        field.span() => compile_error!("A field can't be both a proxy and a map proxy");
+                    })
+                }
+            };
+
+            ret.push(FieldInfo {
+                member,
+                lua_name,
+                ty: field.ty.clone(),
+                kind,
+            });
+        }
+        Ok(ret)
+    }
+
+    /// The key the field is found under in lua: its name for named fields,
+    /// or its one-based position for tuple fields
+    pub(crate) fn key(&self) -> TokenStream2 {
+        match (&self.lua_name, &self.member) {
+            (Some(name), _) => quote!(#name),
+            (None, syn::Member::Unnamed(index)) => {
+                let key = index.index as usize + 1;
+                quote!(#key)
+            }
+            (None, syn::Member::Named(ident)) => {
+                let key = ident.to_string();
+                quote!(#key)
+            }
+        }
+    }
+
+    /// A variable name to bind the field to when destructuring
+    pub(crate) fn binding(&self) -> syn::Ident {
+        match &self.member {
+            syn::Member::Named(ident) => format_ident!("__field_{}", ident),
+            syn::Member::Unnamed(index) => format_ident!("__field_{}", index.index),
+        }
+    }
+}
+
+/// Finds the field of a struct marked `#[lua(sequence)]`, if any, which the
+/// struct behaves as a lua array over
+pub(crate) fn find_sequence(data: &syn::Data) -> Result<Option<syn::Member>, TokenStream2> {
+    let mut ret = None;
+    let fields: Vec<&syn::Field> = match data {
+        syn::Data::Struct(s) => s.fields.iter().collect(),
+        syn::Data::Enum(e) => e.variants.iter().flat_map(|v| v.fields.iter()).collect(),
+        syn::Data::Union(_) => return Ok(None),
+    };
+
+    for (i, field) in fields.into_iter().enumerate() {
+        let attrs = LuaAttrs::parse(&field.attrs, FieldInfo::FLAGS, FieldInfo::VALUES)?;
+        if !attrs.has("sequence") {
+            continue;
+        }
+
+        if let syn::Data::Enum(_) = data {
+            return Err(quote_spanned! {
+                field.span() => compile_error!("Only struct fields can be sequences");
+            });
+        }
+        if ret.is_some() {
+            return Err(quote_spanned! {
+                field.span() => compile_error!("Only one field can be a sequence");
+            });
+        }
+        ret = Some(match &field.ident {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(i.into()),
+        });
+    }
+    Ok(ret)
+}
+
+/// Returns true if the given attribute is one of the attribute macros provided
+/// by this crate which reads `#[lua(...)]` field attributes
+fn is_field_attrs_reader(attr: &syn::Attribute) -> bool {
+    attr.path
+        .segments
+        .last()
+        .map_or(false, |s| s.ident == "metamethods" || s.ident == "user_data")
+}
+
+/// Removes the `#[lua(...)]` attributes from the fields of the given struct or
+/// enum, which would otherwise be rejected by the compiler. Since several of
+/// this crate's attributes can read them, they are left in place while another
+/// one is still to be expanded on the item.
+pub(crate) fn strip_field_attrs(item: &mut syn::Item) {
+    let (attrs, fields): (_, Vec<&mut syn::Fields>) = match item {
+        syn::Item::Struct(s) => (&s.attrs, vec![&mut s.fields]),
+        syn::Item::Enum(e) => (
+            &e.attrs,
+            e.variants.iter_mut().map(|v| &mut v.fields).collect(),
+        ),
+        _ => return,
+    };
+
+    if attrs.iter().any(is_field_attrs_reader) {
+        return;
+    }
+
+    for field in fields.into_iter().flat_map(|f| f.iter_mut()) {
+        LuaAttrs::strip(&mut field.attrs);
+    }
+}
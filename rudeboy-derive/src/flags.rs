@@ -0,0 +1,131 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+
+use crate::metamethods::bitwise_methods;
+
+/// A single flag declared in a `bitflags!` invocation
+struct Flag {
+    name: syn::Ident,
+}
+
+impl Parse for Flag {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.call(syn::Attribute::parse_outer)?;
+        input.parse::<syn::Token![const]>()?;
+        let name = input.parse()?;
+        input.parse::<syn::Token![=]>()?;
+        input.parse::<syn::Expr>()?;
+        input.parse::<syn::Token![;]>()?;
+        Ok(Flag { name })
+    }
+}
+
+/// The struct declared in a `bitflags!` invocation, and its flags
+struct Bitflags {
+    name: syn::Ident,
+    flags: Vec<Flag>,
+}
+
+impl Parse for Bitflags {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.call(syn::Attribute::parse_outer)?;
+        input.parse::<syn::Visibility>()?;
+        input.parse::<syn::Token![struct]>()?;
+        let name = input.parse()?;
+        input.parse::<syn::Token![:]>()?;
+        input.parse::<syn::Type>()?;
+
+        let content;
+        syn::braced!(content in input);
+        let mut flags = Vec::new();
+        while !content.is_empty() {
+            flags.push(content.parse()?);
+        }
+
+        if !input.is_empty() {
+            return Err(input.error("Only a single flags struct can be declared in a bitflags! invocation with user_data"));
+        }
+        Ok(Bitflags { name, flags })
+    }
+}
+
+/// Generates `rlua::UserData` for a type declared with `bitflags!`, with the
+/// bitwise operators, `contains`, `insert` and `remove` methods and a
+/// `tostring` listing the set flags, and a [`RudeboyTable`] of the flags by
+/// name. `extra` is added to the user data's methods.
+pub(crate) fn flags(mac: &syn::ItemMacro, extra: Vec<TokenStream2>) -> TokenStream2 {
+    if mac.mac.path.segments.last().map_or(true, |s| s.ident != "bitflags") {
+        return quote_spanned! {
+            mac.span() => compile_error!("flags can only be applied to a bitflags! invocation");
+        };
+    }
+    let bitflags: Bitflags = match syn::parse2(mac.mac.tokens.clone()) {
+        Ok(bitflags) => bitflags,
+        Err(e) => return e.to_compile_error(),
+    };
+
+    let name = &bitflags.name;
+    let flags: Vec<_> = bitflags.flags.iter().map(|f| &f.name).collect();
+    let flag_names: Vec<_> = flags.iter().map(|f| f.to_string()).collect();
+    let metamethods = bitwise_methods();
+
+    quote! {
+        impl ::rudeboy::RudeboyMetaMethods for #name {
+            #( #metamethods )*
+        }
+
+        impl ::rlua::UserData for #name {
+            fn add_methods<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+                <Self as ::rudeboy::RudeboyMetaMethods>::generate_metamethods(methods);
+
+                methods.add_function("contains", |_, (__this, other): (::rlua::AnyUserData, Self)| {
+                    ::rudeboy::with_ref(&__this, |data: &Self| data.contains(other))
+                });
+                methods.add_function("insert", |_, (__this, other): (::rlua::AnyUserData, Self)| {
+                    ::rudeboy::with_mut(&__this, |data: &mut Self| data.insert(other))
+                });
+                methods.add_function("remove", |_, (__this, other): (::rlua::AnyUserData, Self)| {
+                    ::rudeboy::with_mut(&__this, |data: &mut Self| data.remove(other))
+                });
+                methods.add_meta_function(::rlua::MetaMethod::ToString, |_, __this: ::rlua::AnyUserData| {
+                    ::rudeboy::with_ref(&__this, |data: &Self| {
+                        let mut names: ::std::vec::Vec<&str> = ::std::vec::Vec::new();
+                        #(
+                            if !Self::#flags.is_empty() && data.contains(Self::#flags) {
+                                names.push(#flag_names);
+                            }
+                        )*
+                        if names.is_empty() {
+                            ::std::string::String::from("(empty)")
+                        } else {
+                            names.join(" | ")
+                        }
+                    })
+                });
+
+                #( #extra )*
+            }
+        }
+
+        impl ::rudeboy::RudeboyTable for #name {
+            fn generate_table<'lua>(ctx: ::rlua::Context<'lua>) -> ::rlua::Result<::rlua::Table<'lua>> {
+                let table = ctx.create_table()?;
+                let metatable = ctx.create_table()?;
+                metatable.set("__index", ctx.create_function(|ctx, (_, key): (::rlua::Table, ::rlua::Value)| {
+                    let key = <::rlua::String as ::rlua::FromLua>::from_lua(key, ctx)?;
+                    match key.to_str()? {
+                        #( #flag_names => ::rlua::ToLua::to_lua(Self::#flags, ctx), )*
+                        key => {
+                            use ::rlua::ExternalError;
+                            Err(format!("No such flag: {}", key).to_lua_err())
+                        }
+                    }
+                })?)?;
+                table.set_metatable(Some(metatable));
+                Ok(table)
+            }
+        }
+    }
+}
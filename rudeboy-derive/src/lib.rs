@@ -0,0 +1,282 @@
+//! This crate provides derive and attr macros for use by the [`rudeboy`] crate.
+//! Please refer to it for documentation and usage information.
+//!
+//! [`rudeboy`]: https://docs.rs/rudeboy
+use proc_macro::TokenStream;
+
+mod attrs;
+mod constructors;
+mod fields;
+#[cfg(feature = "bitflags")]
+mod flags;
+mod repr;
+mod variants;
+
+mod methods;
+use methods::impl_methods_attr_macro;
+
+/// Placed on an inherent impl block; generates an impl of [`RudeboyMethods`] to
+/// add the contained methods to the exported user data. Takes no parameters.
+///
+/// A parameter of type `rlua::Context` is passed the context the method is
+/// called in, rather than being converted from a lua argument. It must be
+/// written as `rlua::Context`, or as `Context<'lua>` with its lifetime if
+/// imported, so that other types named `Context` are still converted from lua.
+/// Its lifetime may be named and used in the return type, so that the method
+/// can return values such as an `rlua::Table<'lua>`.
+///
+/// A method returning a `Result` gives lua its value, or `nil` followed by its
+/// error, which must implement `rlua::ToLua`, as rlua does for any function
+/// returning a `Result`. Marking the method `#[lua(raise)]` raises the error in
+/// lua instead, converting it to an `rlua::Error` with `Into`. The error of a
+/// chained or iterated method, or of an index fallback, is always raised.
+///
+/// Other parameters may borrow from lua, such as an `rlua::Function<'lua>` to
+/// be called back before the method returns. The lifetime can be named by the
+/// method, written as `'_`, or left out.
+///
+/// A method taking `self` by value moves the value out of the user data, which
+/// requires the type to implement `Default` for the value left in its place.
+/// Any later use of the user data from lua, including its metamethods, raises
+/// an "already consumed" error.
+///
+/// An impl block for a type with lifetime parameters, exposed to lua with
+/// `rudeboy::scoped`, registers its methods with `add_method` so that they can
+/// borrow the value. Its methods can't be chained or take `self` by value.
+///
+/// Individual methods accept the following `#[lua(...)]` attributes:
+/// * skip - the method is not exported to lua
+/// * iter - the method's return value, which may be any `IntoIterator` such as
+///   a `Vec` or an owned iterator, is passed to `rudeboy::iter` to be returned
+///   as a lua iterator function for use in a generic `for` loop. Tuple items
+///   become multiple loop variables. Since lua ends the loop when the first
+///   loop variable is `nil`, iteration stops early at an item whose first
+///   value converts to `nil`, such as `None`. The iterator must be `'static`,
+///   so it can't borrow from `self`
+/// * cursor - the method, which takes `&self` and a `usize` index and returns
+///   an `Option`, is exported as a method taking no arguments and returning a
+///   lua iterator function. The iterator is built with `rudeboy::cursor`: each
+///   step borrows the user data again and calls the method with the next
+///   index, starting from 0, until it returns `None`. A collection inside the
+///   value can then be iterated without cloning it
+/// * chain - the method's return value is discarded, and the user data it was
+///   called on is returned to lua instead, so that calls can be chained as in
+///   `builder:width(3):height(4)`. Methods returning `&mut Self` are chained
+///   without the attribute. A `Result` is still checked for an error first
+/// * raise - the error of a method returning a `Result` is raised in lua,
+///   rather than returned along with `nil`
+/// * overload = "name" - the method is exported under the given name, which
+///   may be shared with other methods in the block. A call to a shared name
+///   tries each method in the order they are declared, and calls the first one
+///   whose parameter count matches and whose arguments can all be converted.
+///   If none match, an error listing each method's signature is raised
+/// * index_fallback - the method, which takes `&self` and a key such as a
+///   `String`, is called by the Index metamethod generated by `metamethods`
+///   for keys which are neither fields nor methods. Keys of any lua type are
+///   passed on, so a key taken as `rlua::Value` also receives `obj[1]` or
+///   `obj[true]`. Its return value is given to lua, so returning `None` gives
+///   `nil`. The type's `metamethods` must list Index, or have a sequence
+///   field, or the fallback fails to compile. The method isn't exported itself
+/// * new_index_fallback - the method, which takes `&mut self`, a key and a
+///   value, is called by the generated NewIndex metamethod for keys which
+///   aren't fields, as with index_fallback. The type's `metamethods` must list
+///   NewIndex. The method isn't exported itself
+///
+/// A method tagged `#[meta(Name)]`, where `Name` is any `rlua::MetaMethod`
+/// such as `Add`, `ToString` or `Index`, is registered as that metamethod
+/// rather than as a method, with its arguments converted in the same way. The
+/// user data is always its first argument, so a binary operator is only called
+/// when the user data is on the left. Several methods tagged with the same
+/// metamethod are overloaded, as with `overload`. When used with `user_data`,
+/// a tagged method replaces the metamethod of the same name generated by
+/// `metamethods`.
+///
+/// [`RudeboyMethods`]: trait.RudeboyMethods.html
+#[proc_macro_attribute]
+pub fn methods(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::Item);
+    impl_methods_attr_macro(input).into()
+}
+
+mod metamethods;
+use metamethods::impl_metamethods_attr_macro;
+
+/// Placed on a struct or enum definition; generates an impl of
+/// [`RudeboyMetamethods`] to add the specified metamethods to the exported user
+/// data.
+///
+/// Takes any combination of the following parameters:
+/// * Add - allows the use of the `+` operator. Uses `std::ops::Add`
+/// * auto - adds each of the operators and comparisons below, except Index,
+///   NewIndex and Pairs, for which the type implements the std trait. The
+///   arithmetic and bitwise operators are only added for `Clone` types taking
+///   two operands of the type. An operator listed alongside auto is generated
+///   as listed instead. Types with type parameters only get the operators that
+///   are implemented for every instantiation, and types with lifetimes can't
+///   use auto
+/// * BAnd - allows the use of the `&` operator. Uses `std::ops::BitAnd`
+/// * BNot - allows the use of the unary `~` operator. Uses `std::ops::Not`
+/// * BOr - allows the use of the `|` operator. Uses `std::ops::BitOr`
+/// * BXor - allows the use of the binary `~` operator. Uses `std::ops::BitXor`
+/// * Div - allows the use of the `/` operator. Uses `std::ops::Div`
+/// * Eq - allows the use of the `==` operator. Uses `std::cmp::PartialEq`
+/// * Index - allows the use of `.` to retrieve fields. Only usable for structs
+///   with named fields. Unknown keys are an error, unless the type has a method
+///   marked `#[lua(index_fallback)]` in its `methods` block
+/// * Le - allows the use of the `<=` operator. Uses `std::cmp::PartialOrd`
+/// * Lt - allows the use of the `<` operator. Uses `std::cmp::PartialOrd`
+/// * Mod - allows the use of the `%` operator. Uses `std::ops::Rem`
+/// * Mul - allows the use of the `*` operator. Uses `std::ops::Mul`
+/// * NewIndex - allows the use of `.` to assign to fields. Only usable for
+///   structs with named fields, each of which must implement `rlua::FromLua`.
+///   Unknown keys are an error, unless the type has a method marked
+///   `#[lua(new_index_fallback)]`
+/// * Pairs - allows iterating over fields with `pairs`, in declaration order.
+///   For enums, iterates over the fields of the current variant. Tuple fields
+///   are keyed by their one-based position
+/// * Shl - allows the use of the `<<` operator. Uses `std::ops::Shl`
+/// * Shr - allows the use of the `>>` operator. Uses `std::ops::Shr`
+/// * Sub - allows the use of the binary `-` operator. Uses `std::ops::Sub`
+/// * Unm - allows the use of the unary `-` operator. Uses `std::ops::Neg`
+///
+/// The fields exposed by Index, NewIndex and Pairs accept the following `#[lua(...)]`
+/// attributes:
+/// * skip - the field is not exposed to lua
+/// * rename = "name" - the field is exposed under the given name
+/// * map_proxy - reading the field with Index gives a `rudeboy::MapProxy` for
+///   it rather than a copy, so that a `HashMap` or `BTreeMap` field can be
+///   edited in place, as in `obj.stats["hp"] = 10`
+/// * proxy - reading a field whose type is itself user data with Index gives
+///   a `rudeboy::FieldProxy` for it rather than a copy, so that nested reads
+///   and writes, as in `obj.pos.x = 3`, reach the original object. Assigning
+///   to the field with NewIndex also accepts such a proxy
+///
+/// A single `Vec` field of a struct can be marked `#[lua(sequence)]`, which
+/// makes the struct behave as a lua array over that field, whether or not any
+/// metamethods are listed. `obj[i]` reads and assigns elements using one-based
+/// indices, `#obj` gives the length, `ipairs(obj)` iterates over the elements,
+/// and the `push`, `pop` and `insert` methods add and remove them. Reading
+/// outside of the sequence gives `nil`, as it would for a table, and assigning
+/// anywhere other than an existing index or one past the end is an error. If
+/// Index is also listed, non-integer keys look up fields as usual. The `push`,
+/// `pop` and `insert` methods are registered by
+/// `RudeboyMetaMethods::generate_sequence_methods` rather than with the length
+/// metamethod, so a hand-written `generate_len` doesn't remove them.
+///
+/// By default, binary operators take two operands of the type the metamethod
+/// is being added to. The arithmetic and bitwise operators can instead list the
+/// types of operands they accept, as in `Mul(f64)` or `Add(Self, i32)`, where
+/// `Self` stands for two values of the type. The user data may be on either
+/// side: `v * 2.0` uses `impl Mul<f64> for Vec2`, and `2.0 * v` uses
+/// `impl Mul<Vec2> for f64` if there is one. The operands are checked at runtime
+/// against each listed type in order, and any other operands are an error.
+///
+/// Listing `by_ref` instead, as in `Add(by_ref)`, borrows both operands in
+/// place and uses the operator on references, such as `impl Add for &BigInt`,
+/// so that neither operand is cloned and the type need not be `Clone`.
+///
+/// Operands are borrowed with `rudeboy::with_ref`, so either side may also be a
+/// `rudeboy::Shared` or `rudeboy::FieldProxy` holding the type. The arithmetic
+/// and bitwise operators clone their operands out of lua, and so require
+/// `Clone`, unless they're listed with `by_ref`; `Eq`, `Lt` and `Le` compare
+/// them in place.
+///
+/// A type with lifetime parameters, exposed to lua with `rudeboy::scoped`, can
+/// only have Index, NewIndex and Pairs generated. The other operand of a binary
+/// operator can't be borrowed, and the result of Unm or BNot, usually another
+/// value of the type, can't be given to lua, as neither is `'static`. Its
+/// fields can't be sequences or proxies.
+///
+/// [`RudeboyMetaMethods`]: trait.RudeboyMetaMethods.html
+#[proc_macro_attribute]
+pub fn metamethods(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::Item);
+    use syn::parse::Parser;
+    let parser = syn::punctuated::Punctuated::<metamethods::MetaMethodArg, syn::Token!(,)>::parse_terminated;
+    let attrs = match parser.parse(attr) {
+        Ok(ok) => ok.into_iter().collect(),
+        Err(e) => return e.to_compile_error().into(),
+    };
+    impl_metamethods_attr_macro(input, attrs).into()
+}
+
+mod user_data;
+use user_data::impl_user_data_attr_macro;
+
+/// Generates an implementation of `rlua::UserData` for the tagged type
+/// definition or the type that matches a tagged impl block.
+///
+/// Takes zero or more of the following parameters. If given none, then the
+/// exported type will have no methods or metamethods available, other than
+/// those generated for enums, described below.
+/// * MetaMethods - will use the [`RudeboyMetaMethods`] trait to add generated
+///   meta methods
+/// * Methods - will use the [`RudeboyMethods`] trait to add generated methods
+/// * Constructors - for enums. Implements [`RudeboyTable`], giving a table
+///   of the enum's variants by name so that scripts can create values:
+///   `Foo.Bar` for a variant without fields, `Foo.Baz(5)` for a tuple variant
+///   and `Foo.Qux{ x = 2, y = 3 }` for a struct variant. Fields are converted
+///   with `rlua::FromLua`, and a wrong argument count, a field that can't be
+///   converted, an unknown field or an unknown variant raise an error naming
+///   the constructor. A generic enum gets a table for each instantiation, as
+///   in `Foo::<i32>::generate_table`, and can't have lifetime parameters
+/// * Match - for enums. Generates a `match` method, described below
+/// * as_string - for enums without fields. Rather than `rlua::UserData`,
+///   generates `rlua::ToLua` and `rlua::FromLua`, converting each variant to
+///   and from a string of its name. Converting any other string is an error
+///   listing the valid names. Can't be combined with the other parameters
+///   except rename_all
+/// * rename_all = "rule" - renames the variants of an as_string enum, using
+///   one of the rules "lowercase", "UPPERCASE", "PascalCase", "camelCase",
+///   "snake_case", "SCREAMING_SNAKE_CASE", "kebab-case" or
+///   "SCREAMING-KEBAB-CASE"
+/// * as_integer - for enums without fields. As with as_string, but converts
+///   each variant to and from its discriminant, including explicit ones such
+///   as `Red = 1`. Converting any other integer is an error listing the valid
+///   values. Also implements [`RudeboyTable`], giving a table of the
+///   discriminants by variant name, so that scripts can write `Color.Red`
+/// * flags - with the bitflags feature, placed on a `bitflags!` invocation
+///   declaring a single flags type. Generates the BAnd, BOr, BXor, BNot and Eq
+///   metamethods with the same generators as [`metamethods`], `contains`,
+///   `insert` and `remove` methods taking another value of the type, and a
+///   `tostring` listing the names of the set flags. Also implements
+///   [`RudeboyTable`], giving a table of the flags by name. Can only be
+///   combined with Methods
+///
+/// Given Match, an enum gets a `match` method, since lua has no match
+/// statement: `value:match{ Bar = function() end, Baz = function(n) end,
+/// _ = function() end }` calls the function named after the current variant
+/// with the variant's fields as arguments, in declaration order and leaving
+/// out those marked `#[lua(skip)]`. The fields are cloned and converted with
+/// `rlua::ToLua`, so every field that isn't skipped must be `Clone` and
+/// `ToLua`, which is why the method is opt-in. If there is no function for the
+/// variant, `_` is called without arguments, and if there is no `_` either, an
+/// error is raised.
+///
+/// Enums always get an `is_<variant>()` method for each variant, with the variant
+/// name in snake case, such as `bit:is_overflow()`, as well as
+/// `variant_name()`, giving the current variant's name, and `variant_index()`,
+/// giving its zero-based position in the enum definition. Methods exported by
+/// [`methods`] take the place of any of these with the same name.
+///
+/// Note: if you wish to add additional (meta)methods beyond the ones generated
+/// by rudeboy, do not use this macro and instead manually call the appropriate
+/// trait methods in your implementation of `rlua::UserData`
+///
+/// [`RudeboyMetaMethods`]: trait.RudeboyMetaMethods.html
+/// [`RudeboyMethods`]: trait.RudeboyMethods.html
+/// [`RudeboyTable`]: trait.RudeboyTable.html
+/// [`methods`]: attr.methods.html
+/// [`metamethods`]: attr.metamethods.html
+#[proc_macro_attribute]
+pub fn user_data(attr: TokenStream, item: TokenStream) -> TokenStream {
+    use syn::parse::Parser;
+    let parser = syn::punctuated::Punctuated::<syn::NestedMeta, syn::Token!(,)>::parse_terminated;
+    let parsed_attrs = parser.parse(attr);
+    let attrs = match &parsed_attrs {
+        Ok(ok) => ok.iter().collect(),
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let input = syn::parse_macro_input!(item as syn::Item);
+    impl_user_data_attr_macro(input, attrs).into()
+}
@@ -0,0 +1,819 @@
+use quote::{format_ident, quote, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use proc_macro2::TokenStream as TokenStream2;
+
+use crate::fields::{find_sequence, strip_field_attrs, FieldInfo, FieldKind};
+
+fn operator_method(name: TokenStream2, rlua_enum: TokenStream2, operator: TokenStream2) -> TokenStream2 {
+    quote! {
+        fn #name<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+            ::rudeboy::__private::binary(
+                methods,
+                ::rlua::MetaMethod::#rlua_enum,
+                |lhs: Self, rhs: Self| lhs #operator rhs,
+            );
+        }
+    }
+}
+
+/// Generates a binary operator taking the user data and a value of any of the
+/// given types, on either side. The operands are tried against each type in
+/// order; `Self` stands for two values of the user data. When the user data is
+/// on the right, the operator is only called if the other type implements it,
+/// which is checked with the autoref probe for `probe`
+fn mixed_operator_method(
+    name: TokenStream2,
+    rlua_enum: TokenStream2,
+    operator: TokenStream2,
+    probe: &str,
+    operands: &[syn::Type],
+) -> TokenStream2 {
+    let tag = format_ident!("{}Op", probe);
+    let probe = format_ident!("{}Probe", probe);
+    let checks = operands.iter().map(|ty| match ty {
+        syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident("Self") => quote! {
+            if let (Some(lhs), Some(rhs)) = (&__lhs, &__rhs) {
+                return ::rlua::ToLua::to_lua(lhs.clone() #operator rhs.clone(), ctx);
+            }
+        },
+        ty => quote! {
+            if let Some(data) = &__lhs {
+                if let Ok(other) = <#ty as ::rlua::FromLua>::from_lua(rhs.clone(), ctx) {
+                    return ::rlua::ToLua::to_lua(data.clone() #operator other, ctx);
+                }
+            }
+            if let Some(data) = &__rhs {
+                if let Ok(other) = <#ty as ::rlua::FromLua>::from_lua(lhs.clone(), ctx) {
+                    let probe = ::rudeboy::__private::Probe::<::rudeboy::__private::#tag, #ty, Self>::new();
+                    if let Some(ret) = (&probe).apply(ctx, other, data.clone()) {
+                        return ret;
+                    }
+                }
+            }
+        },
+    });
+    let metamethod = rlua_enum.to_string();
+    quote! {
+        fn #name<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+            methods.add_meta_function(
+                ::rlua::MetaMethod::#rlua_enum,
+                |ctx, (lhs, rhs): (::rlua::Value, ::rlua::Value)| {
+                    #[allow(unused_imports)]
+                    use ::rudeboy::__private::{Fallback as _, #probe as _};
+                    let __lhs = ::rudeboy::__private::operand::<Self>(&lhs)?;
+                    let __rhs = ::rudeboy::__private::operand::<Self>(&rhs)?;
+                    #( #checks )*
+                    Err(::rudeboy::__private::unsupported_operands(#metamethod, &lhs, &rhs))
+                },
+            );
+        }
+    }
+}
+
+/// Generates a binary operator borrowing both operands, used for comparisons
+/// and for arithmetic on references
+fn ref_operator_method(name: TokenStream2, rlua_enum: TokenStream2, operator: TokenStream2) -> TokenStream2 {
+    quote! {
+        fn #name<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+            ::rudeboy::__private::binary_ref(
+                methods,
+                ::rlua::MetaMethod::#rlua_enum,
+                |lhs: &Self, rhs: &Self| lhs #operator rhs,
+            );
+        }
+    }
+}
+
+fn unary_operator_method(name: TokenStream2, rlua_enum: TokenStream2, operator: TokenStream2) -> TokenStream2 {
+    quote! {
+        fn #name<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+            ::rudeboy::__private::unary(
+                methods,
+                ::rlua::MetaMethod::#rlua_enum,
+                |value: Self| #operator value,
+            );
+        }
+    }
+}
+
+/// The bitwise operator metamethods and Eq, generated for types declared with
+/// the `bitflags!` macro
+#[cfg(feature = "bitflags")]
+pub(crate) fn bitwise_methods() -> Vec<TokenStream2> {
+    vec![
+        operator_method(quote!(generate_band), quote!(BAnd), quote!(&)),
+        operator_method(quote!(generate_bor), quote!(BOr), quote!(|)),
+        operator_method(quote!(generate_bxor), quote!(BXor), quote!(^)),
+        unary_operator_method(quote!(generate_bnot), quote!(BNot), quote!(!)),
+        ref_operator_method(quote!(generate_eq), quote!(Eq), quote!(==)),
+    ]
+}
+
+/// The error for an operator metamethod on a type with lifetimes. A binary
+/// operator would have to borrow the other operand as `Self`, and a unary one
+/// would have to give its result, usually another `Self`, to lua, neither of
+/// which can be done for a type that isn't `'static`
+fn scoped_operator_error(ast: &syn::DeriveInput, metamethod: &str) -> TokenStream2 {
+    let msg = format!("{} metamethod can't be generated for types with lifetimes", metamethod);
+    quote_spanned! {
+        ast.generics.span() => compile_error!(#msg);
+    }
+}
+
+/// Parses the fields that the given metamethod looks up by name, which it can
+/// only do for structs with named fields
+fn named_fields(ast: &syn::DeriveInput, metamethod: &str, scoped: bool) -> Result<Vec<FieldInfo>, TokenStream2> {
+    let struct_ =
+        match &ast.data {
+            syn::Data::Struct(s) => s,
+            _ => {
+                let msg = format!("{} metamethod can only be applied to structs", metamethod);
+                return Err(quote_spanned! {
+                    ast.span() => compile_error!(#msg);
+                });
+            },
+        };
+
+    let fields = &struct_.fields;
+
+    let mut bad_struct = true;
+    if let syn::Fields::Named(_) = fields {
+        bad_struct = false;
+    }
+
+    if fields.is_empty() {
+        bad_struct = true;
+    }
+
+    if bad_struct {
+        let msg = format!("{} metamethod can only be applied to structs with named fields", metamethod);
+        return Err(quote_spanned! {
+            fields.span() => compile_error!(#msg);
+        });
+    }
+
+    let fields = FieldInfo::parse_all(fields)?;
+    if scoped && fields.iter().any(|f| f.kind != FieldKind::Value) {
+        return Err(quote_spanned! {
+            ast.generics.span() => compile_error!("Fields of types with lifetimes can't be proxies");
+        });
+    }
+    Ok(fields)
+}
+
+/// Generates the index metamethod. Looks up fields by name if `fields` is
+/// set, and elements of the sequence field by integer, if there is one. A
+/// `scoped` type's fields are read from the value rlua borrows for the call.
+fn index_method(ast: &syn::DeriveInput, fields: bool, sequence: Option<&syn::Member>, scoped: bool) -> TokenStream2 {
+    let fields = if fields {
+        match named_fields(ast, "Index", scoped) {
+            Ok(fields) => fields,
+            Err(e) => return e,
+        }
+    } else {
+        Vec::new()
+    };
+    let reads = fields.iter().map(|f| {
+        if scoped {
+            let member = &f.member;
+            quote!(::rlua::ToLua::to_lua(data.#member.clone(), ctx))
+        } else {
+            read_field(f)
+        }
+    });
+    let keys = fields.iter().map(|f| f.key());
+
+    let sequence = sequence.map(|member| quote! {
+        if let Some(index) = ::rudeboy::__private::sequence_key(&index) {
+            let value = ::rudeboy::with_ref(&__this, |data: &Self| {
+                ::rudeboy::__private::sequence_get(&data.#member, index)
+            })?;
+            return ::rlua::ToLua::to_lua(value, ctx);
+        }
+    });
+
+    // Keys which aren't fields are passed to the type's index fallback, if it
+    // has one
+    let fallback = if scoped {
+        quote!((&probe).index_fallback(data, ctx, key))
+    } else {
+        quote!(::rudeboy::with_ref(&__this, |data: &Self| (&probe).index_fallback(data, ctx, key))?)
+    };
+
+    let (register, params) = if scoped {
+        (quote!(add_meta_method), quote!(ctx, data, index: ::rlua::Value))
+    } else {
+        (
+            quote!(add_meta_function),
+            quote!(ctx, (__this, index): (::rlua::AnyUserData, ::rlua::Value)),
+        )
+    };
+
+    quote! {
+        fn generate_index<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+            methods.#register(
+                ::rlua::MetaMethod::Index,
+                |#params| {
+                    #sequence
+                    // Only string keys can name fields, but any key may be
+                    // handled by the fallback
+                    let index_str = match &index {
+                        ::rlua::Value::String(s) => Some(s.to_str()?),
+                        _ => None,
+                    };
+                    #(
+                        if index_str == Some(#keys) {
+                            #reads
+                        } else
+                    )*
+                    {
+                        #[allow(unused_imports)]
+                        use ::rudeboy::__private::{IndexFallbackProbe as _, NoFallback as _};
+                        let probe = ::rudeboy::__private::Probe::<::rudeboy::__private::FallbackOp, Self, Self>::new();
+                        let key = index.clone();
+                        match #fallback {
+                            Some(value) => value,
+                            None => Err(::rudeboy::__private::no_index(&index)),
+                        }
+                    }
+                },
+            );
+        }
+    }
+}
+
+/// An expression reading the given field out of the user data `__this` as a
+/// lua value
+fn read_field(field: &FieldInfo) -> TokenStream2 {
+    let member = &field.member;
+    match field.kind {
+        FieldKind::Value => quote! {
+            ::rlua::ToLua::to_lua(::rudeboy::with_ref(&__this, |data: &Self| data.#member.clone())?, ctx)
+        },
+        FieldKind::MapProxy => quote! {
+            ::rlua::ToLua::to_lua(
+                ::rudeboy::MapProxy::create(ctx, __this, |data: &mut Self| &mut data.#member)?,
+                ctx,
+            )
+        },
+        FieldKind::Proxy => quote! {
+            ::rlua::ToLua::to_lua(
+                ::rudeboy::FieldProxy::create(
+                    ctx,
+                    __this,
+                    |data: &Self| &data.#member,
+                    |data: &mut Self| &mut data.#member,
+                )?,
+                ctx,
+            )
+        },
+    }
+}
+
+/// Generates the new index metamethod. Assigns to fields by name if `fields`
+/// is set, and to elements of the sequence field by integer, if there is one
+fn new_index_method(ast: &syn::DeriveInput, fields: bool, sequence: Option<&syn::Member>, scoped: bool) -> TokenStream2 {
+    let fields = if fields {
+        match named_fields(ast, "NewIndex", scoped) {
+            Ok(fields) => fields,
+            Err(e) => return e,
+        }
+    } else {
+        Vec::new()
+    };
+    let values = fields.iter().map(|f| {
+        let ty = &f.ty;
+        match f.kind {
+            // The value may be a proxy itself, or a Shared, so it's borrowed
+            // through whichever it is rather than converted directly
+            FieldKind::Proxy => quote! {
+                match value {
+                    ::rlua::Value::UserData(value) => ::rudeboy::with_ref(&value, |value: &#ty| value.clone())?,
+                    value => <#ty as ::rlua::FromLua>::from_lua(value, ctx)?,
+                }
+            },
+            FieldKind::Value | FieldKind::MapProxy => quote! {
+                <#ty as ::rlua::FromLua>::from_lua(value, ctx)?
+            },
+        }
+    });
+    let keys = fields.iter().map(|f| f.key());
+    let assigns = fields.iter().map(|f| {
+        let member = &f.member;
+        if scoped {
+            quote!({
+                data.#member = value;
+                Ok(())
+            })
+        } else {
+            quote!(::rudeboy::with_mut(&__this, |data: &mut Self| data.#member = value))
+        }
+    });
+    let fallback = if scoped {
+        quote!((&probe).new_index_fallback(data, ctx, key, value))
+    } else {
+        quote! {
+            ::rudeboy::with_mut(&__this, |data: &mut Self| (&probe).new_index_fallback(data, ctx, key, value))?
+        }
+    };
+    let (register, params) = if scoped {
+        (
+            quote!(add_meta_method_mut),
+            quote!(ctx, data, (index, value): (::rlua::Value, ::rlua::Value)),
+        )
+    } else {
+        (
+            quote!(add_meta_function),
+            quote!(ctx, (__this, index, value): (::rlua::AnyUserData, ::rlua::Value, ::rlua::Value)),
+        )
+    };
+
+    let sequence = sequence.map(|member| quote! {
+        if let Some(index) = ::rudeboy::__private::sequence_key(&index) {
+            let value = ::rlua::FromLua::from_lua(value, ctx)?;
+            return ::rudeboy::with_mut(&__this, |data: &mut Self| {
+                ::rudeboy::__private::sequence_set(&mut data.#member, index, value)
+            })?;
+        }
+    });
+
+    quote! {
+        fn generate_new_index<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+            methods.#register(
+                ::rlua::MetaMethod::NewIndex,
+                |#params| {
+                    #sequence
+                    let index_str = match &index {
+                        ::rlua::Value::String(s) => Some(s.to_str()?),
+                        _ => None,
+                    };
+                    #(
+                        if index_str == Some(#keys) {
+                            let value = #values;
+                            #assigns
+                        } else
+                    )*
+                    {
+                        #[allow(unused_imports)]
+                        use ::rudeboy::__private::{NewIndexFallbackProbe as _, NoFallback as _};
+                        let probe = ::rudeboy::__private::Probe::<::rudeboy::__private::FallbackOp, Self, Self>::new();
+                        let key = index.clone();
+                        match #fallback {
+                            Some(result) => result,
+                            None => Err(::rudeboy::__private::no_index(&index)),
+                        }
+                    }
+                },
+            );
+        }
+    }
+}
+
+/// Generates the length metamethod and the `push`, `pop` and `insert` methods
+/// for a struct behaving as a lua array over the given field
+fn sequence_methods(member: &syn::Member) -> TokenStream2 {
+    quote! {
+        fn generate_len<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+            ::rudeboy::__private::sequence_len(methods, |data: &Self| &data.#member);
+        }
+
+        fn generate_sequence_methods<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+            ::rudeboy::__private::sequence_methods(methods, |data: &mut Self| &mut data.#member);
+        }
+    }
+}
+
+/// Builds the list of key/value pairs for the given fields, reading each one
+/// from its binding if `bound` is set, or through `data` otherwise
+fn pairs_entries(fields: &[FieldInfo], bound: bool) -> TokenStream2 {
+    let entries = fields.iter().map(|f| {
+        let key = f.key();
+        let value = if bound {
+            let binding = f.binding();
+            quote!(#binding)
+        } else {
+            let member = &f.member;
+            quote!(data.#member)
+        };
+        quote! {
+            (::rlua::ToLua::to_lua(#key, ctx)?, ::rlua::ToLua::to_lua(#value.clone(), ctx)?)
+        }
+    });
+    quote!(vec![#( #entries ),*])
+}
+
+fn pairs_method(ast: &syn::DeriveInput, scoped: bool) -> TokenStream2 {
+    let entries = match &ast.data {
+        syn::Data::Struct(s) => match FieldInfo::parse_all(&s.fields) {
+            Ok(fields) => pairs_entries(&fields, false),
+            Err(e) => return e,
+        },
+        syn::Data::Enum(e) => {
+            let mut arms = Vec::new();
+            for variant in &e.variants {
+                let fields = match FieldInfo::parse_all(&variant.fields) {
+                    Ok(fields) => fields,
+                    Err(e) => return e,
+                };
+                let name = &variant.ident;
+                let members = fields.iter().map(|f| &f.member);
+                let bindings = fields.iter().map(|f| f.binding());
+                let entries = pairs_entries(&fields, true);
+                arms.push(quote! {
+                    Self::#name { #( #members: #bindings, )* .. } => #entries,
+                });
+            }
+            quote! {
+                match data {
+                    #( #arms )*
+                }
+            }
+        }
+        syn::Data::Union(_) => {
+            return quote_spanned! {
+                ast.span() => compile_error!("Pairs metamethod can only be applied to structs and enums");
+            }
+        }
+    };
+
+    if scoped {
+        return quote! {
+            fn generate_pairs<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+                methods.add_meta_method(::rlua::MetaMethod::Pairs, |ctx, data, ()| {
+                    let entries: ::std::vec::Vec<(::rlua::Value, ::rlua::Value)> = #entries;
+                    ::rudeboy::__private::pairs(ctx, entries)
+                });
+            }
+        };
+    }
+
+    quote! {
+        fn generate_pairs<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+            methods.add_meta_function(::rlua::MetaMethod::Pairs, |ctx, __this: ::rlua::AnyUserData| {
+                let entries = ::rudeboy::with_ref(&__this, |data: &Self| {
+                    let entries: ::std::vec::Vec<(::rlua::Value, ::rlua::Value)> = #entries;
+                    Ok(entries)
+                })??;
+                ::rudeboy::__private::pairs(ctx, entries)
+            });
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+enum MetaMethod {
+    Add,
+    Eq,
+    Index,
+    NewIndex,
+    Pairs,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Unm,
+    BAnd,
+    BOr,
+    BXor,
+    BNot,
+    Shl,
+    Shr,
+    Lt,
+    Le,
+}
+
+impl MetaMethod {
+    const ADD_IDENT: &'static str = "Add";
+    const EQUALS_IDENT: &'static str = "Eq";
+    const INDEX_IDENT: &'static str = "Index";
+    const NEW_INDEX_IDENT: &'static str = "NewIndex";
+    const PAIRS_IDENT: &'static str = "Pairs";
+    const SUB_IDENT: &'static str = "Sub";
+    const MUL_IDENT: &'static str = "Mul";
+    const DIV_IDENT: &'static str = "Div";
+    const MOD_IDENT: &'static str = "Mod";
+    const UNM_IDENT: &'static str = "Unm";
+    const BAND_IDENT: &'static str = "BAnd";
+    const BOR_IDENT: &'static str = "BOr";
+    const BXOR_IDENT: &'static str = "BXor";
+    const BNOT_IDENT: &'static str = "BNot";
+    const SHL_IDENT: &'static str = "Shl";
+    const SHR_IDENT: &'static str = "Shr";
+    const LT_IDENT: &'static str = "Lt";
+    const LE_IDENT: &'static str = "Le";
+
+    fn try_parse(path: &syn::Path) -> Result<MetaMethod, TokenStream2> {
+        if path.is_ident(Self::ADD_IDENT) {
+            Ok(MetaMethod::Add)
+        } else if path.is_ident(Self::EQUALS_IDENT) {
+            Ok(MetaMethod::Eq)
+        } else if path.is_ident(Self::INDEX_IDENT) {
+            Ok(MetaMethod::Index)
+        } else if path.is_ident(Self::NEW_INDEX_IDENT) {
+            Ok(MetaMethod::NewIndex)
+        } else if path.is_ident(Self::PAIRS_IDENT) {
+            Ok(MetaMethod::Pairs)
+        } else if path.is_ident(Self::SUB_IDENT) {
+            Ok(MetaMethod::Sub)
+        } else if path.is_ident(Self::MUL_IDENT) {
+            Ok(MetaMethod::Mul)
+        } else if path.is_ident(Self::DIV_IDENT) {
+            Ok(MetaMethod::Div)
+        } else if path.is_ident(Self::MOD_IDENT) {
+            Ok(MetaMethod::Mod)
+        } else if path.is_ident(Self::UNM_IDENT) {
+            Ok(MetaMethod::Unm)
+        } else if path.is_ident(Self::BAND_IDENT) {
+            Ok(MetaMethod::BAnd)
+        } else if path.is_ident(Self::BOR_IDENT) {
+            Ok(MetaMethod::BOr)
+        } else if path.is_ident(Self::BXOR_IDENT) {
+            Ok(MetaMethod::BXor)
+        } else if path.is_ident(Self::BNOT_IDENT) {
+            Ok(MetaMethod::BNot)
+        } else if path.is_ident(Self::SHL_IDENT) {
+            Ok(MetaMethod::Shl)
+        } else if path.is_ident(Self::SHR_IDENT) {
+            Ok(MetaMethod::Shr)
+        } else if path.is_ident(Self::LT_IDENT) {
+            Ok(MetaMethod::Lt)
+        } else if path.is_ident(Self::LE_IDENT) {
+            Ok(MetaMethod::Le)
+        } else {
+            Err(quote_spanned! {
+                path.span() => compile_error!("Expected a valid metamethod identifier");
+            })
+        }
+    }
+    
+    /// The generated function, rlua metamethod, rust operator and autoref probe
+    /// prefix of the arithmetic and bitwise binary operators
+    fn binary_operator(&self) -> Option<(TokenStream2, TokenStream2, TokenStream2, &'static str)> {
+        Some(match self {
+            MetaMethod::Add => (quote!(generate_add), quote!(Add), quote!(+), "Add"),
+            MetaMethod::Sub => (quote!(generate_sub), quote!(Sub), quote!(-), "Sub"),
+            MetaMethod::Mul => (quote!(generate_mul), quote!(Mul), quote!(*), "Mul"),
+            MetaMethod::Div => (quote!(generate_div), quote!(Div), quote!(/), "Div"),
+            MetaMethod::Mod => (quote!(generate_mod), quote!(Mod), quote!(%), "Rem"),
+            MetaMethod::BAnd => (quote!(generate_band), quote!(BAnd), quote!(&), "BitAnd"),
+            MetaMethod::BOr => (quote!(generate_bor), quote!(BOr), quote!(|), "BitOr"),
+            MetaMethod::BXor => (quote!(generate_bxor), quote!(BXor), quote!(^), "BitXor"),
+            MetaMethod::Shl => (quote!(generate_shl), quote!(Shl), quote!(<<), "Shl"),
+            MetaMethod::Shr => (quote!(generate_shr), quote!(Shr), quote!(>>), "Shr"),
+            _ => return None,
+        })
+    }
+
+    /// The metamethods generated by `auto` when their std trait is implemented
+    const AUTO: [MetaMethod; 15] = [
+        MetaMethod::Eq,
+        MetaMethod::Add,
+        MetaMethod::Sub,
+        MetaMethod::Mul,
+        MetaMethod::Div,
+        MetaMethod::Mod,
+        MetaMethod::Unm,
+        MetaMethod::BAnd,
+        MetaMethod::BOr,
+        MetaMethod::BXor,
+        MetaMethod::BNot,
+        MetaMethod::Shl,
+        MetaMethod::Shr,
+        MetaMethod::Lt,
+        MetaMethod::Le,
+    ];
+
+    /// Generates the metamethod for `auto`, which is only registered if the
+    /// type implements the std trait, using the autoref probe for it
+    fn auto_method(&self) -> TokenStream2 {
+        let (name, tag) = match self {
+            MetaMethod::Eq => (quote!(generate_eq), "Eq"),
+            MetaMethod::Unm => (quote!(generate_unm), "Neg"),
+            MetaMethod::BNot => (quote!(generate_bnot), "Not"),
+            MetaMethod::Lt => (quote!(generate_lt), "Lt"),
+            MetaMethod::Le => (quote!(generate_le), "Le"),
+            _ => {
+                let (name, _, _, probe) = self
+                    .binary_operator()
+                    .expect("every other automatic metamethod is a binary operator");
+                (name, probe)
+            }
+        };
+        let tag = format_ident!("{}Op", tag);
+        let auto = format_ident!("Auto{}", format!("{:?}", self));
+        quote! {
+            fn #name<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+                #[allow(unused_imports)]
+                use ::rudeboy::__private::{AutoFallback as _, #auto as _};
+                (&::rudeboy::__private::Probe::<::rudeboy::__private::#tag, Self, Self>::new()).register(methods);
+            }
+        }
+    }
+
+    fn get_method(&self, ast: &syn::DeriveInput, operands: &Operands, scoped: bool) -> TokenStream2 {
+        match &self {
+            MetaMethod::Index => index_method(ast, true, None, scoped),
+            MetaMethod::NewIndex => new_index_method(ast, true, None, scoped),
+            MetaMethod::Pairs => pairs_method(ast, scoped),
+            _ if scoped => scoped_operator_error(ast, &format!("{:?}", self)),
+            MetaMethod::Unm => unary_operator_method(quote!(generate_unm), quote!(Unm), quote!(-)),
+            MetaMethod::BNot => unary_operator_method(quote!(generate_bnot), quote!(BNot), quote!(!)),
+            MetaMethod::Eq =>
+                ref_operator_method(quote!(generate_eq), quote!(Eq), quote!(==)),
+            MetaMethod::Lt => ref_operator_method(quote!(generate_lt), quote!(Lt), quote!(<)),
+            MetaMethod::Le => ref_operator_method(quote!(generate_le), quote!(Le), quote!(<=)),
+            _ => {
+                let (name, rlua_enum, operator, probe) = self
+                    .binary_operator()
+                    .expect("every other metamethod is a binary operator");
+                match operands {
+                    Operands::Owned => operator_method(name, rlua_enum, operator),
+                    Operands::ByRef => ref_operator_method(name, rlua_enum, operator),
+                    Operands::Mixed(types) => mixed_operator_method(name, rlua_enum, operator, probe, types),
+                }
+            }
+        }
+    }
+}
+
+/// The operands a binary operator metamethod accepts
+enum Operands {
+    /// Two values of the user data, cloned out of lua
+    Owned,
+    /// Two values of the user data, borrowed in place
+    ByRef,
+    /// The user data and a value of any of the listed types, on either side
+    Mixed(Vec<syn::Type>),
+}
+
+/// One parameter of the metamethods attribute: a metamethod's name, optionally
+/// followed by the types of operands it accepts, as in `Mul(f64)`
+pub(crate) struct MetaMethodArg {
+    ident: syn::Ident,
+    operands: Option<Punctuated<syn::Type, syn::Token![,]>>,
+}
+
+impl Parse for MetaMethodArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident = input.parse()?;
+        let operands = if input.peek(syn::token::Paren) {
+            let content;
+            syn::parenthesized!(content in input);
+            Some(content.parse_terminated(syn::Type::parse)?)
+        } else {
+            None
+        };
+        Ok(MetaMethodArg { ident, operands })
+    }
+}
+
+/// Parses the listed metamethods, and whether `auto` was given
+fn attrs_to_metamethods(
+    attrs: Vec<MetaMethodArg>,
+) -> Result<(Vec<(MetaMethod, Operands)>, bool), TokenStream2> {
+    let mut metamethods: Vec<(MetaMethod, Operands)> = Vec::new();
+    let mut auto = false;
+    for attr in attrs {
+        if attr.ident == "auto" {
+            if attr.operands.is_some() {
+                return Err(quote_spanned! {
+                    attr.ident.span() => compile_error!("auto doesn't take operand types");
+                });
+            }
+            auto = true;
+            continue;
+        }
+        let metamethod = MetaMethod::try_parse(&syn::Path::from(attr.ident.clone()))?;
+        let operands = match attr.operands {
+            None => Operands::Owned,
+            Some(types) => {
+                if metamethod.binary_operator().is_none() {
+                    let msg = format!("{:?} metamethod doesn't take operand types", metamethod);
+                    return Err(quote_spanned! {
+                        attr.ident.span() => compile_error!(#msg);
+                    });
+                }
+                if types.is_empty() {
+                    return Err(quote_spanned! {
+                        attr.ident.span() => compile_error!("Expected at least one operand type");
+                    });
+                }
+                let by_ref = |ty: &syn::Type| match ty {
+                    syn::Type::Path(p) => p.qself.is_none() && p.path.is_ident("by_ref"),
+                    _ => false,
+                };
+                if types.len() == 1 && by_ref(&types[0]) {
+                    Operands::ByRef
+                } else if let Some(ty) = types.iter().find(|ty| by_ref(ty)) {
+                    return Err(quote_spanned! {
+                        ty.span() => compile_error!("by_ref can't be combined with operand types");
+                    });
+                } else {
+                    Operands::Mixed(types.into_iter().collect())
+                }
+            }
+        };
+        metamethods.retain(|(mm, _)| *mm != metamethod);
+        metamethods.push((metamethod, operands));
+    }
+    Ok((metamethods, auto))
+}
+
+pub(crate) fn impl_metamethods_attr_macro(
+    mut item: syn::Item,
+    attrs: Vec<MetaMethodArg>,
+) -> TokenStream2 {
+    let di = match &item {
+        syn::Item::Struct(s) => syn::DeriveInput::from(s.clone()),
+        syn::Item::Enum(e) => syn::DeriveInput::from(e.clone()),
+        _ => {
+            return quote_spanned! {
+                item.span() => compile_error!("metamethods can only be applied to structs and enums");
+            }
+        }
+    };
+    let name = &di.ident;
+    let sequence = match find_sequence(&di.data) {
+        Ok(sequence) => sequence,
+        Err(e) => return e,
+    };
+    let (metamethods, auto) = match attrs_to_metamethods(attrs) {
+        Ok(mms) => mms,
+        Err(e) => return e,
+    };
+
+    // A type with lifetimes can only be exposed to lua through rudeboy::scoped,
+    // which can't be borrowed back from an AnyUserData, so its metamethods are
+    // registered with add_meta_method and borrowed by rlua instead
+    let scoped = di.generics.lifetimes().next().is_some();
+    if scoped && sequence.is_some() {
+        return quote_spanned! {
+            di.generics.span() => compile_error!("Types with lifetimes can't have a sequence field");
+        };
+    }
+    if scoped && auto {
+        return quote_spanned! {
+            di.generics.span() => compile_error!("auto metamethods can't be generated for types with lifetimes");
+        };
+    }
+
+    // A sequence field needs the index and new index metamethods, so they're
+    // generated here rather than by get_method, handling fields as well if
+    // requested
+    let mut methods: Vec<_> = metamethods
+        .iter()
+        .filter(|(mm, _)| {
+            sequence.is_none() || (*mm != MetaMethod::Index && *mm != MetaMethod::NewIndex)
+        })
+        .map(|(mm, operands)| mm.get_method(&di, operands, scoped))
+        .collect();
+    // Metamethods which are listed explicitly take precedence over auto
+    if auto {
+        methods.extend(
+            MetaMethod::AUTO
+                .iter()
+                .filter(|auto| metamethods.iter().all(|(mm, _)| mm != *auto))
+                .map(MetaMethod::auto_method),
+        );
+    }
+    if let Some(member) = &sequence {
+        let index = metamethods.iter().any(|(mm, _)| *mm == MetaMethod::Index);
+        let new_index = metamethods.iter().any(|(mm, _)| *mm == MetaMethod::NewIndex);
+        methods.push(index_method(&di, index, Some(member), false));
+        methods.push(new_index_method(&di, new_index, Some(member), false));
+        methods.push(sequence_methods(member));
+    }
+
+    strip_field_attrs(&mut item);
+
+    // Index fallbacks can only be given to types whose Index or NewIndex is
+    // generated here, as they're never called otherwise
+    let (impl_generics, ty_generics, where_clause) = di.generics.split_for_impl();
+    let lists = |mm: MetaMethod| metamethods.iter().any(|(listed, _)| *listed == mm);
+    let generated_index = if lists(MetaMethod::Index) || sequence.is_some() {
+        Some(quote! {
+            impl #impl_generics ::rudeboy::__private::GeneratedIndex for #name #ty_generics #where_clause {}
+        })
+    } else {
+        None
+    };
+    let generated_new_index = if lists(MetaMethod::NewIndex) || sequence.is_some() {
+        Some(quote! {
+            impl #impl_generics ::rudeboy::__private::GeneratedNewIndex for #name #ty_generics #where_clause {}
+        })
+    } else {
+        None
+    };
+    quote! {
+        #item
+
+        impl #impl_generics ::rudeboy::RudeboyMetaMethods for #name #ty_generics #where_clause {
+            #( #methods )*
+        }
+
+        #generated_index
+        #generated_new_index
+    }
+}
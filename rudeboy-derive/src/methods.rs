@@ -0,0 +1,659 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::visit_mut::VisitMut;
+
+use crate::attrs::LuaAttrs;
+
+struct Param<'a> {
+    name: &'a syn::Ident,
+    /// The parameter's type, with the method's own lifetime parameters elided
+    /// so that they are inferred where the argument is converted
+    ty: syn::Type,
+    /// Whether this parameter is an `rlua::Context`, which is passed the
+    /// context the method is called in rather than a lua argument
+    is_context: bool,
+}
+
+/// The lookups a method marked as an index fallback handles
+#[derive(PartialEq, Clone, Copy)]
+enum Fallback {
+    /// `#[lua(index_fallback)]`, reading a key that isn't a field
+    Index,
+    /// `#[lua(new_index_fallback)]`, assigning to a key that isn't a field
+    NewIndex,
+}
+
+/// How a method takes `self`
+#[derive(PartialEq)]
+enum ReceiverKind {
+    Ref,
+    Mut,
+    /// The method takes `self` by value, moving it out of the user data
+    Value,
+}
+
+struct MethodInfo<'a> {
+    pub name: &'a syn::Ident,
+    pub lua_name: String,
+    pub receiver: ReceiverKind,
+    pub params: Vec<Param<'a>>,
+    /// Whether the method returns a `Result`
+    pub returns_result: bool,
+    /// Whether an error returned by the method is raised in lua, rather than
+    /// returned as `nil, err`
+    pub raises: bool,
+    /// Whether the method's return value is turned into a lua iterator
+    pub is_iter: bool,
+    /// Whether the method reads the item at an index, and is exported as a
+    /// lua iterator calling it with each index in turn
+    pub is_cursor: bool,
+    /// Whether the method's return value is discarded, and the user data it was
+    /// called on returned in its place
+    pub is_chain: bool,
+    /// The metamethod the method is registered as, if it is tagged with
+    /// `#[meta(...)]`
+    pub meta: Option<syn::Ident>,
+    /// Whether the method is called by the generated Index or NewIndex for
+    /// unknown keys, rather than being exported
+    pub fallback: Option<Fallback>,
+}
+
+impl<'a> MethodInfo<'a> {
+    const FLAGS: &'static [&'static str] = &["skip", "iter", "cursor", "chain", "raise", "index_fallback", "new_index_fallback"];
+    const VALUES: &'static [&'static str] = &["overload"];
+    const META_IDENT: &'static str = "meta";
+
+    /// Parses a method from a `#[methods]` block, returning `None` if it is
+    /// marked to be skipped
+    fn try_parse(method: &'a syn::ImplItemMethod) -> Result<Option<Self>, TokenStream2> {
+        let attrs = LuaAttrs::parse(&method.attrs, Self::FLAGS, Self::VALUES)?;
+        if attrs.has("skip") {
+            return Ok(None);
+        }
+
+        let signature = &method.sig;
+        let name = &signature.ident;
+        use syn::FnArg::*;
+        let receiver = match signature.receiver() {
+            Some(Receiver(rcv)) => rcv,
+            Some(Typed(_)) => {
+                return Err(quote_spanned! {
+                    signature.span() => compile_error!("Cannot currently handle typed receivers (i.e., a receiver other than self, &self or &mut self)");
+                })
+            }
+            None => {
+                return Err(quote_spanned! {
+                    signature.span() => compile_error!("Cannot currently handle class level methods");
+                })
+            }
+        };
+        let receiver = match (&receiver.reference, receiver.mutability) {
+            (None, _) => ReceiverKind::Value,
+            (Some(_), None) => ReceiverKind::Ref,
+            (Some(_), Some(_)) => ReceiverKind::Mut,
+        };
+
+        let mut lifetimes = ElideLifetimes(
+            signature
+                .generics
+                .lifetimes()
+                .map(|l| l.lifetime.clone())
+                .collect(),
+        );
+        let params = signature
+            .inputs
+            .iter()
+            .skip(1)
+            .map(|arg| get_param_from_fn_arg(arg, &mut lifetimes))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut metas = method.attrs.iter().filter(|a| a.path.is_ident(Self::META_IDENT));
+        let meta = match metas.next() {
+            Some(attr) => Some(attr.parse_args::<syn::Ident>().map_err(|e| e.to_compile_error())?),
+            None => None,
+        };
+        if let Some(attr) = metas.next() {
+            return Err(quote_spanned! {
+                attr.span() => compile_error!("A method can only be registered as one metamethod");
+            });
+        }
+
+        let lua_name = match (&meta, attrs.value("overload")) {
+            (Some(_), Some(overload)) => {
+                return Err(quote_spanned! {
+                    overload.span() => compile_error!("A metamethod can't also be given an overload name");
+                })
+            }
+            (Some(meta), None) => meta.to_string(),
+            (None, Some(overload)) => overload.value(),
+            (None, None) => name.to_string(),
+        };
+
+        let returns_result = match &signature.output {
+            syn::ReturnType::Type(_, ty) => is_named(ty, "Result"),
+            syn::ReturnType::Default => false,
+        };
+
+        let returns_self = match &signature.output {
+            syn::ReturnType::Type(_, ty) => is_mut_self(ty),
+            syn::ReturnType::Default => false,
+        };
+        let is_chain = attrs.has("chain") || returns_self;
+        let is_iter = attrs.has("iter");
+        if is_chain && is_iter {
+            return Err(quote_spanned! {
+                signature.span() => compile_error!("A method can't both be chained and return an iterator");
+            });
+        }
+        if is_chain && receiver == ReceiverKind::Value {
+            return Err(quote_spanned! {
+                signature.span() => compile_error!("A method that moves self can't be chained");
+            });
+        }
+
+        let is_cursor = attrs.has("cursor");
+        if is_cursor {
+            if is_chain || is_iter || meta.is_some() || attrs.value("overload").is_some() {
+                return Err(quote_spanned! {
+                    signature.span() => compile_error!("A cursor can't be chained, iterated, overloaded or registered as a metamethod");
+                });
+            }
+            if receiver != ReceiverKind::Ref || params.len() != 1 || params[0].is_context || returns_result {
+                return Err(quote_spanned! {
+                    signature.span() => compile_error!("A cursor must take &self and an index, and return an Option");
+                });
+            }
+        }
+
+        if attrs.has("raise") && !returns_result {
+            return Err(quote_spanned! {
+                signature.span() => compile_error!("Only a method returning a Result can raise its error");
+            });
+        }
+        // Only a plain return value can be given to lua as `nil, err`, so the
+        // error of a chained or iterated method is always raised
+        let raises = returns_result && (attrs.has("raise") || is_chain || is_iter);
+
+        let fallback = match (attrs.has("index_fallback"), attrs.has("new_index_fallback")) {
+            (false, false) => None,
+            (true, false) => Some(Fallback::Index),
+            (false, true) => Some(Fallback::NewIndex),
+            (true, true) => {
+                return Err(quote_spanned! {
+                    signature.span() => compile_error!("A method can't be both index fallbacks");
+                })
+            }
+        };
+        if let Some(fallback) = fallback {
+            if is_chain || is_iter || is_cursor || meta.is_some() || attrs.value("overload").is_some() {
+                return Err(quote_spanned! {
+                    signature.span() => compile_error!("An index fallback can't be chained, iterated, overloaded or registered as a metamethod");
+                });
+            }
+            let (receiver_kind, count, msg) = match fallback {
+                Fallback::Index => (
+                    ReceiverKind::Ref,
+                    1,
+                    "An index fallback must take &self and a key",
+                ),
+                Fallback::NewIndex => (
+                    ReceiverKind::Mut,
+                    2,
+                    "A new index fallback must take &mut self, a key and a value",
+                ),
+            };
+            if receiver != receiver_kind || params.len() != count || params.iter().any(|p| p.is_context) {
+                return Err(quote_spanned! {
+                    signature.span() => compile_error!(#msg);
+                });
+            }
+        }
+
+        Ok(Some(MethodInfo {
+            name,
+            lua_name,
+            receiver,
+            params,
+            returns_result,
+            raises,
+            is_iter,
+            is_cursor,
+            is_chain,
+            meta,
+            fallback,
+        }))
+    }
+
+    /// The parameters converted from lua arguments
+    fn lua_params(&self) -> impl Iterator<Item = &Param<'a>> {
+        self.params.iter().filter(|p| !p.is_context)
+    }
+
+    /// The arguments the method is called with, and the type they are
+    /// converted to from lua, including the user data the method is called on
+    /// unless the method is `scoped`
+    fn args(&self, scoped: bool) -> (TokenStream2, TokenStream2) {
+        let names = self.lua_params().map(|p| p.name);
+        let tys = self.lua_params().map(|p| &p.ty);
+        if scoped {
+            (
+                quote!((#( #names, )*)),
+                quote!((#( #tys, )*)),
+            )
+        } else {
+            (
+                quote!((__this, #( #names, )*)),
+                quote!((::rlua::AnyUserData, #( #tys, )*)),
+            )
+        }
+    }
+
+    /// An expression calling the method on `__this`, evaluating to an
+    /// `rlua::Result` of its return value. A `scoped` method is instead called
+    /// on `__data`, the value rlua has already borrowed.
+    fn call(&self, scoped: bool) -> TokenStream2 {
+        let name = self.name;
+        let names = self.params.iter().map(|p| {
+            if p.is_context {
+                quote!(__ctx)
+            } else {
+                let name = p.name;
+                quote!(#name)
+            }
+        });
+        // A chained method's return value borrows from the user data, so it is
+        // dropped before the borrow ends, keeping only a `Result`'s error
+        let ret = match (self.is_chain, self.returns_result) {
+            (false, _) => quote!(__data.#name(#( #names ),*)),
+            (true, false) => quote!({ __data.#name(#( #names ),*); }),
+            (true, true) => quote!(__data.#name(#( #names ),*).map(|_| ())),
+        };
+        let call = match self.receiver {
+            _ if scoped => quote!(::std::result::Result::Ok::<_, ::rlua::Error>(#ret)),
+            ReceiverKind::Ref => quote!(::rudeboy::with_ref(&__this, |__data: &Self| #ret)),
+            ReceiverKind::Mut => quote!(::rudeboy::with_mut(&__this, |__data: &mut Self| #ret)),
+            ReceiverKind::Value => quote! {
+                ::rudeboy::__private::consume::<Self>(&__this).map(|__data| #ret)
+            },
+        };
+
+        let call = if self.raises {
+            quote!(#call.and_then(|__ret| __ret.map_err(::std::convert::Into::into)))
+        } else {
+            call
+        };
+
+        if self.is_iter {
+            quote!(#call.and_then(|__ret| ::rudeboy::iter(__ctx, __ret)))
+        } else if self.is_chain {
+            quote!(#call.map(|()| __this.clone()))
+        } else {
+            call
+        }
+    }
+
+    /// A readable description of the method's lua signature
+    fn signature(&self) -> String {
+        let params: Vec<_> = self
+            .lua_params()
+            .map(|p| {
+                let ty = &p.ty;
+                format!("{}: {}", p.name, type_to_string(&quote!(#ty)))
+            })
+            .collect();
+        format!("{}({})", self.lua_name, params.join(", "))
+    }
+}
+
+fn type_to_string(ty: &TokenStream2) -> String {
+    ty.to_string()
+        .replace(" :: ", "::")
+        .replace(":: ", "::")
+        .replace(" < ", "<")
+        .replace(" <", "<")
+        .replace("< ", "<")
+        .replace(" >", ">")
+        .replace(" ,", ",")
+        .replace("& ", "&")
+        .replace("' ", "'")
+}
+
+/// Returns true if the given type is a path ending in the given name. Only the
+/// last path segment is checked, so the type may be imported under its own
+/// name or used through any path.
+fn is_named(ty: &syn::Type, name: &str) -> bool {
+    match ty {
+        syn::Type::Path(p) if p.qself.is_none() => {
+            p.path.segments.last().map_or(false, |s| s.ident == name)
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if the given type is `rlua::Context`, either through the `rlua`
+/// path or imported as `Context` and given its lifetime, as in `Context<'lua>`.
+/// Other types named `Context` are converted from lua as usual.
+fn is_context(ty: &syn::Type) -> bool {
+    let path = match ty {
+        syn::Type::Path(p) if p.qself.is_none() => &p.path,
+        _ => return false,
+    };
+    let segments: Vec<_> = path.segments.iter().collect();
+    match segments.as_slice() {
+        [rlua, context] => rlua.ident == "rlua" && context.ident == "Context",
+        [context] => {
+            context.ident == "Context"
+                && match &context.arguments {
+                    syn::PathArguments::AngleBracketed(args) => args
+                        .args
+                        .iter()
+                        .any(|arg| matches!(arg, syn::GenericArgument::Lifetime(_))),
+                    _ => false,
+                }
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if the given type is `&mut Self`, as returned by builder
+/// methods
+fn is_mut_self(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Reference(r) if r.mutability.is_some() => match r.elem.as_ref() {
+            syn::Type::Path(p) => p.qself.is_none() && p.path.is_ident("Self"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Replaces the given lifetimes with `'_`. The lifetime parameters of a method
+/// aren't in scope in the generated code, but the types they appear in, such
+/// as `rlua::Function<'lua>`, can be converted with the lifetime inferred.
+struct ElideLifetimes(Vec<syn::Lifetime>);
+
+impl VisitMut for ElideLifetimes {
+    fn visit_lifetime_mut(&mut self, lifetime: &mut syn::Lifetime) {
+        if self.0.contains(lifetime) {
+            *lifetime = syn::Lifetime::new("'_", lifetime.span());
+        }
+    }
+}
+
+fn get_param_from_fn_arg<'a>(
+    fn_arg: &'a syn::FnArg,
+    lifetimes: &mut ElideLifetimes,
+) -> Result<Param<'a>, TokenStream2> {
+    if let syn::FnArg::Typed(t) = fn_arg {
+        let pat: &syn::Pat = t.pat.as_ref();
+        if let syn::Pat::Ident(i) = pat {
+            let mut ty = t.ty.as_ref().clone();
+            lifetimes.visit_type_mut(&mut ty);
+            Ok(Param {
+                name: &i.ident,
+                is_context: is_context(&ty),
+                ty,
+            })
+        } else {
+            Err(quote_spanned! {
+                pat.span() => compile_error!("Expected an identifier here. This is probably a bug.");
+            })
+        }
+    } else {
+        Err(quote_spanned! {
+            fn_arg.span() => compile_error!("Expected a typed argument of the form 'ident: Type'. This is a bug.");
+        })
+    }
+}
+
+/// The `UserDataMethods` function used to register the given methods, and
+/// the name or metamethod they are registered under. The function is
+/// `add_function` unless they are `scoped`, in which case rlua borrows the
+/// value itself, or the metamethod equivalent for a method tagged `#[meta]`
+fn register(methods: &[MethodInfo], scoped: bool) -> (TokenStream2, TokenStream2) {
+    let (kind, key) = match &methods[0].meta {
+        Some(meta) => ("meta_", quote!(::rlua::MetaMethod::#meta)),
+        None => {
+            let lua_name = &methods[0].lua_name;
+            ("", quote!(#lua_name))
+        }
+    };
+    let register = if !scoped {
+        format_ident!("add_{}function", kind)
+    } else if methods.iter().any(|m| m.receiver == ReceiverKind::Mut) {
+        format_ident!("add_{}method_mut", kind)
+    } else {
+        format_ident!("add_{}method", kind)
+    };
+    (quote!(#register), key)
+}
+
+/// The parameters taken by a function given to `register`
+fn closure_params(scoped: bool) -> TokenStream2 {
+    if scoped {
+        quote!(__ctx, __data, __args: ::rlua::MultiValue<'lua>)
+    } else {
+        quote!(__ctx, __args: ::rlua::MultiValue<'lua>)
+    }
+}
+
+/// Registers a single method under its lua name
+fn single_method(m: &MethodInfo, scoped: bool) -> TokenStream2 {
+    let (args, tys) = m.args(scoped);
+    let call = m.call(scoped);
+    let (register, key) = register(std::slice::from_ref(m), scoped);
+    let params = closure_params(scoped);
+    quote! {
+        _methods.#register(#key, |#params| {
+            let #args: #tys = ::rlua::FromLuaMulti::from_lua_multi(__args, __ctx)?;
+            #call
+        });
+    }
+}
+
+/// Registers a dispatcher for several methods sharing one lua name, which
+/// calls the first whose argument count and types match the arguments given
+fn overloaded_method(overloads: &[MethodInfo], scoped: bool) -> TokenStream2 {
+    let lua_name = &overloads[0].lua_name;
+    let candidates = overloads.iter().map(|m| {
+        let (args, tys) = m.args(scoped);
+        let call = m.call(scoped);
+        let count = m.lua_params().count() + if scoped { 0 } else { 1 };
+        quote! {
+            if __args.len() == #count {
+                if let Ok(#args) = <#tys as ::rlua::FromLuaMulti>::from_lua_multi(__args.clone(), __ctx) {
+                    return ::rlua::ToLuaMulti::to_lua_multi(#call?, __ctx);
+                }
+            }
+        }
+    });
+    let signatures = overloads.iter().map(|m| m.signature());
+    let (register, key) = register(overloads, scoped);
+    let params = closure_params(scoped);
+
+    quote! {
+        _methods.#register(#key, |#params| {
+            #( #candidates )*
+            Err(::rudeboy::__private::no_overload(#lua_name, &[#( #signatures ),*], &__args))
+        });
+    }
+}
+
+/// Registers a cursor method under its lua name, as a function returning an
+/// iterator that calls the method with each index in turn. Each item is
+/// converted while the value is borrowed, so it may borrow from it
+fn cursor_method(m: &MethodInfo) -> TokenStream2 {
+    let name = m.name;
+    let lua_name = &m.lua_name;
+    quote! {
+        _methods.add_function(#lua_name, |__ctx, __this: ::rlua::AnyUserData| {
+            ::rudeboy::__private::cursor_lua(__ctx, __this, |__ctx, __data: &Self, __index| {
+                match __data.#name(__index) {
+                    ::std::option::Option::Some(__item) => {
+                        ::rlua::ToLuaMulti::to_lua_multi(__item, __ctx).map(::std::option::Option::Some)
+                    }
+                    ::std::option::Option::None => Ok(::std::option::Option::None),
+                }
+            })
+        });
+    }
+}
+
+/// Implements the trait through which the generated Index or NewIndex calls
+/// the given fallback method for unknown keys
+fn fallback_impl(m: &MethodInfo, ast: &syn::ItemImpl) -> TokenStream2 {
+    let name = m.name;
+    let names: Vec<_> = m.params.iter().map(|p| p.name).collect();
+    let tys: Vec<_> = m.params.iter().map(|p| &p.ty).collect();
+    let call = quote!(self.#name(#( #names ),*));
+    let self_ty = &ast.self_ty;
+    let (impl_generics, _, where_clause) = ast.generics.split_for_impl();
+    match m.fallback {
+        Some(Fallback::Index) => {
+            let ret = if m.returns_result {
+                quote!(#call.map_err(::std::convert::Into::<::rlua::Error>::into)?)
+            } else {
+                call
+            };
+            let (key, key_ty) = (names[0], tys[0]);
+            quote! {
+                impl #impl_generics ::rudeboy::__private::IndexFallback for #self_ty #where_clause {
+                    fn index_fallback<'lua>(
+                        &self,
+                        __ctx: ::rlua::Context<'lua>,
+                        __key: ::rlua::Value<'lua>,
+                    ) -> ::rlua::Result<::rlua::Value<'lua>> {
+                        let #key = <#key_ty as ::rlua::FromLua>::from_lua(__key, __ctx)?;
+                        ::rlua::ToLua::to_lua(#ret, __ctx)
+                    }
+                }
+            }
+        }
+        Some(Fallback::NewIndex) => {
+            let ret = if m.returns_result {
+                quote!(#call.map(|_| ()).map_err(::std::convert::Into::into))
+            } else {
+                quote!({
+                    #call;
+                    Ok(())
+                })
+            };
+            let (key, key_ty, value, value_ty) = (names[0], tys[0], names[1], tys[1]);
+            quote! {
+                impl #impl_generics ::rudeboy::__private::NewIndexFallback for #self_ty #where_clause {
+                    fn new_index_fallback<'lua>(
+                        &mut self,
+                        __ctx: ::rlua::Context<'lua>,
+                        __key: ::rlua::Value<'lua>,
+                        __value: ::rlua::Value<'lua>,
+                    ) -> ::rlua::Result<()> {
+                        let #key = <#key_ty as ::rlua::FromLua>::from_lua(__key, __ctx)?;
+                        let #value = <#value_ty as ::rlua::FromLua>::from_lua(__value, __ctx)?;
+                        #ret
+                    }
+                }
+            }
+        }
+        None => unreachable!("only called for fallback methods"),
+    }
+}
+
+fn implitem_methods_attr_macro(mut ast: syn::ItemImpl) -> TokenStream2 {
+    // Methods grouped by the name they are exported to lua under, in the order
+    // each name first appears
+    let mut groups: Vec<Vec<MethodInfo>> = Vec::new();
+
+    // A type with lifetimes can only be exposed to lua through rudeboy::scoped,
+    // which can't be borrowed back from an AnyUserData, so its methods are
+    // registered with add_method and borrowed by rlua instead
+    let scoped = ast.generics.lifetimes().next().is_some();
+    let mut fallbacks: Vec<TokenStream2> = Vec::new();
+    let mut cursors: Vec<TokenStream2> = Vec::new();
+    let mut seen_fallbacks: Vec<Fallback> = Vec::new();
+
+    for item in &ast.items {
+        if let syn::ImplItem::Method(m) = item {
+            let method = match MethodInfo::try_parse(m) {
+                Ok(Some(method)) => method,
+                Ok(None) => continue,
+                Err(ts) => return ts,
+            };
+
+            if scoped && (method.is_chain || method.is_cursor || method.receiver == ReceiverKind::Value) {
+                return quote_spanned! {
+                    m.sig.span() => compile_error!("Methods of types with lifetimes can't be chained, be cursors or move self");
+                };
+            }
+
+            if method.is_cursor {
+                cursors.push(cursor_method(&method));
+                continue;
+            }
+
+            if let Some(fallback) = method.fallback {
+                if seen_fallbacks.contains(&fallback) {
+                    return quote_spanned! {
+                        m.sig.span() => compile_error!("An impl block can only have one fallback of each kind");
+                    };
+                }
+                seen_fallbacks.push(fallback);
+                fallbacks.push(fallback_impl(&method, &ast));
+                continue;
+            }
+
+            // Metamethods are grouped apart from methods, so that a method may
+            // share the name of a metamethod
+            match groups
+                .iter_mut()
+                .find(|g| g[0].lua_name == method.lua_name && g[0].meta.is_some() == method.meta.is_some())
+            {
+                Some(group) => group.push(method),
+                None => groups.push(vec![method]),
+            }
+        }
+    }
+
+    let mqs: Vec<_> = groups
+        .iter()
+        .map(|group| {
+            if group.len() == 1 {
+                single_method(&group[0], scoped)
+            } else {
+                overloaded_method(group, scoped)
+            }
+        })
+        .collect();
+
+    for item in ast.items.iter_mut() {
+        if let syn::ImplItem::Method(m) = item {
+            LuaAttrs::strip(&mut m.attrs);
+            m.attrs.retain(|a| !a.path.is_ident(MethodInfo::META_IDENT));
+        }
+    }
+
+    let self_ty = &ast.self_ty;
+    let (impl_generics, _, where_clause) = ast.generics.split_for_impl();
+    quote! {
+        #ast
+
+        impl #impl_generics ::rudeboy::RudeboyMethods for #self_ty #where_clause {
+            fn generate_methods<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(_methods: &mut M) {
+                #( #mqs )*
+                #( #cursors )*
+            }
+        }
+
+        #( #fallbacks )*
+    }
+}
+
+pub(crate) fn impl_methods_attr_macro(item: syn::Item) -> TokenStream2 {
+    if let syn::Item::Impl(i) = item {
+        implitem_methods_attr_macro(i)
+    } else {
+        quote_spanned! {
+            item.span() => compile_error!("Methods macro can only be applied to an inherent impl block");
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+/// Returns the variants of the given enum, checking that none of them have
+/// fields, as required by the given `user_data` parameter
+fn unit_variants<'a>(e: &'a syn::ItemEnum, param: &str) -> Result<Vec<&'a syn::Ident>, TokenStream2> {
+    let mut ret = Vec::new();
+    for variant in &e.variants {
+        if !variant.fields.is_empty() {
+            let msg = format!("{} can only be applied to enums without fields", param);
+            return Err(quote_spanned! {
+                variant.fields.span() => compile_error!(#msg);
+            });
+        }
+        ret.push(&variant.ident);
+    }
+    Ok(ret)
+}
+
+/// Splits a variant name into its words, at each change from lower to upper
+/// case, at the start of the last capital of an acronym, and at underscores
+fn words(ident: &str) -> Vec<String> {
+    let chars: Vec<char> = ident.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        let prev = if i > 0 { Some(chars[i - 1]) } else { None };
+        let next = chars.get(i + 1);
+        let boundary = c.is_uppercase()
+            && match prev {
+                Some(p) if p.is_lowercase() || p.is_ascii_digit() => true,
+                Some(p) if p.is_uppercase() => next.map_or(false, |n| n.is_lowercase()),
+                _ => false,
+            };
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Capitalizes the first letter of a word and lowercases the rest
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }
+}
+
+/// Renames a variant according to a `rename_all` rule, with the same rule
+/// names as serde. Returns `None` for an unknown rule.
+pub(crate) fn rename(ident: &str, rule: &str) -> Option<String> {
+    let words = words(ident);
+    let lower: Vec<String> = words.iter().map(|w| w.to_lowercase()).collect();
+    let upper: Vec<String> = words.iter().map(|w| w.to_uppercase()).collect();
+    Some(match rule {
+        "lowercase" => lower.concat(),
+        "UPPERCASE" => upper.concat(),
+        "PascalCase" => words.iter().map(|w| capitalize(w)).collect(),
+        "camelCase" => {
+            let mut ret = lower.first().cloned().unwrap_or_default();
+            ret.extend(words.iter().skip(1).map(|w| capitalize(w)));
+            ret
+        }
+        "snake_case" => lower.join("_"),
+        "SCREAMING_SNAKE_CASE" => upper.join("_"),
+        "kebab-case" => lower.join("-"),
+        "SCREAMING-KEBAB-CASE" => upper.join("-"),
+        _ => return None,
+    })
+}
+
+/// Generates `ToLua` and `FromLua` for a fieldless enum, converting each
+/// variant to and from its name, as changed by the `rename_all` rule if given
+pub(crate) fn as_string(e: &syn::ItemEnum, rename_all: Option<&syn::LitStr>) -> TokenStream2 {
+    let variants = match unit_variants(e, "as_string") {
+        Ok(variants) => variants,
+        Err(e) => return e,
+    };
+
+    let mut names = Vec::new();
+    for variant in &variants {
+        let ident = variant.to_string();
+        names.push(match rename_all {
+            Some(rule) => match rename(&ident, &rule.value()) {
+                Some(name) => name,
+                None => {
+                    return quote_spanned! {
+                        rule.span() => compile_error!("Unknown rename_all rule, expected one of \"lowercase\", \"UPPERCASE\", \"PascalCase\", \"camelCase\", \"snake_case\", \"SCREAMING_SNAKE_CASE\", \"kebab-case\" or \"SCREAMING-KEBAB-CASE\"");
+                    }
+                }
+            },
+            None => ident,
+        });
+    }
+
+    let name = &e.ident;
+    let type_name = name.to_string();
+    quote! {
+        impl<'lua> ::rlua::ToLua<'lua> for #name {
+            fn to_lua(self, ctx: ::rlua::Context<'lua>) -> ::rlua::Result<::rlua::Value<'lua>> {
+                let name = match self {
+                    #( Self::#variants => #names, )*
+                };
+                ::rlua::ToLua::to_lua(name, ctx)
+            }
+        }
+
+        impl<'lua> ::rlua::FromLua<'lua> for #name {
+            fn from_lua(value: ::rlua::Value<'lua>, ctx: ::rlua::Context<'lua>) -> ::rlua::Result<Self> {
+                let name = <::rlua::String as ::rlua::FromLua>::from_lua(value, ctx)?;
+                match name.to_str()? {
+                    #( #names => Ok(Self::#variants), )*
+                    name => Err(::rudeboy::__private::unknown_variant(
+                        "string",
+                        #type_name,
+                        name,
+                        &[#( #names ),*],
+                    )),
+                }
+            }
+        }
+    }
+}
+
+/// Generates `ToLua` and `FromLua` for a fieldless enum, converting each
+/// variant to and from its discriminant, and a table of the discriminants by
+/// variant name
+pub(crate) fn as_integer(e: &syn::ItemEnum) -> TokenStream2 {
+    let variants = match unit_variants(e, "as_integer") {
+        Ok(variants) => variants,
+        Err(e) => return e,
+    };
+    let names = variants.iter().map(|v| v.to_string());
+
+    let name = &e.ident;
+    let type_name = name.to_string();
+    quote! {
+        impl<'lua> ::rlua::ToLua<'lua> for #name {
+            fn to_lua(self, ctx: ::rlua::Context<'lua>) -> ::rlua::Result<::rlua::Value<'lua>> {
+                ::rlua::ToLua::to_lua(self as i64, ctx)
+            }
+        }
+
+        impl<'lua> ::rlua::FromLua<'lua> for #name {
+            fn from_lua(value: ::rlua::Value<'lua>, ctx: ::rlua::Context<'lua>) -> ::rlua::Result<Self> {
+                let value = <i64 as ::rlua::FromLua>::from_lua(value, ctx)?;
+                #(
+                    if value == Self::#variants as i64 {
+                        return Ok(Self::#variants);
+                    }
+                )*
+                Err(::rudeboy::__private::unknown_variant(
+                    "integer",
+                    #type_name,
+                    value,
+                    &[#( Self::#variants as i64 ),*],
+                ))
+            }
+        }
+
+        impl ::rudeboy::RudeboyTable for #name {
+            fn generate_table<'lua>(ctx: ::rlua::Context<'lua>) -> ::rlua::Result<::rlua::Table<'lua>> {
+                let table = ctx.create_table()?;
+                #( table.set(#names, Self::#variants as i64)?; )*
+                Ok(table)
+            }
+        }
+    }
+}
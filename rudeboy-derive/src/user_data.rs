@@ -0,0 +1,287 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use std::collections::HashSet;
+use syn::spanned::Spanned;
+
+use crate::constructors;
+use crate::fields::strip_field_attrs;
+use crate::repr;
+use crate::variants;
+
+#[derive(Eq, PartialEq, Hash)]
+enum UserDataAttr {
+    MetaMethods,
+    Methods,
+    /// The enum is converted to and from a string rather than user data
+    AsString,
+    /// The enum is converted to and from an integer rather than user data
+    AsInteger,
+    /// A table of constructors for the enum's variants is generated
+    Constructors,
+    /// The enum is given a `match` method taking a table of handlers
+    Match,
+    /// The type is declared with `bitflags!`, and is given bitwise operators
+    /// and methods for working with its flags
+    Flags,
+    /// The rule used to rename variants converted to strings
+    RenameAll(syn::LitStr),
+}
+
+impl UserDataAttr {
+    const META_METHODS_IDENT: &'static str = "MetaMethods";
+    const METHODS_IDENT: &'static str = "Methods";
+    const CONSTRUCTORS_IDENT: &'static str = "Constructors";
+    const MATCH_IDENT: &'static str = "Match";
+    const AS_STRING_IDENT: &'static str = "as_string";
+    const AS_INTEGER_IDENT: &'static str = "as_integer";
+    const FLAGS_IDENT: &'static str = "flags";
+    const RENAME_ALL_IDENT: &'static str = "rename_all";
+
+    fn try_parse(path: &syn::Path) -> Result<UserDataAttr, TokenStream2> {
+        if path.is_ident(Self::META_METHODS_IDENT) {
+            Ok(UserDataAttr::MetaMethods)
+        } else if path.is_ident(Self::METHODS_IDENT) {
+            Ok(UserDataAttr::Methods)
+        } else if path.is_ident(Self::CONSTRUCTORS_IDENT) {
+            Ok(UserDataAttr::Constructors)
+        } else if path.is_ident(Self::MATCH_IDENT) {
+            Ok(UserDataAttr::Match)
+        } else if path.is_ident(Self::AS_STRING_IDENT) {
+            Ok(UserDataAttr::AsString)
+        } else if path.is_ident(Self::AS_INTEGER_IDENT) {
+            Ok(UserDataAttr::AsInteger)
+        } else if path.is_ident(Self::FLAGS_IDENT) {
+            Ok(UserDataAttr::Flags)
+        } else {
+            Err(quote_spanned! {
+                path.span() => compile_error!("Expected a valid metamethod identifier");
+            })
+        }
+    }
+
+    fn get_code(&self, name: TokenStream2) -> Option<TokenStream2> {
+        match self {
+            UserDataAttr::MetaMethods => Some(quote! {
+                <#name as ::rudeboy::RudeboyMetaMethods>::generate_metamethods(methods);
+            }),
+            UserDataAttr::Methods => Some(quote! {
+                <#name as ::rudeboy::RudeboyMethods>::generate_methods(methods);
+            }),
+            UserDataAttr::Constructors
+            | UserDataAttr::Match
+            | UserDataAttr::AsString
+            | UserDataAttr::AsInteger
+            | UserDataAttr::Flags
+            | UserDataAttr::RenameAll(_) => None,
+        }
+    }
+}
+
+fn attrs_to_user_data_attrs(
+    attrs: Vec<&syn::NestedMeta>,
+) -> Result<HashSet<UserDataAttr>, TokenStream2> {
+    let mut ret = HashSet::new();
+    for attr in attrs {
+        use syn::{Lit, Meta, MetaNameValue, NestedMeta};
+        ret.insert(match attr {
+            NestedMeta::Meta(Meta::Path(p)) => UserDataAttr::try_parse(p)?,
+            NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit: Lit::Str(s), .. }))
+                if path.is_ident(UserDataAttr::RENAME_ALL_IDENT) =>
+            {
+                UserDataAttr::RenameAll(s.clone())
+            }
+            _ => {
+                return Err(quote_spanned! {
+                    attr.span() => compile_error!("Expected a valid user_data identifier");
+                })
+            }
+        });
+    }
+    Ok(ret)
+}
+
+/// Generates the user data implementation for a `bitflags!` invocation
+#[cfg(feature = "bitflags")]
+fn flags(item: &syn::Item, attrs: &HashSet<UserDataAttr>) -> TokenStream2 {
+    let mac = match item {
+        syn::Item::Macro(mac) => mac,
+        _ => {
+            return quote_spanned! {
+                item.span() => compile_error!("flags can only be applied to a bitflags! invocation");
+            }
+        }
+    };
+    if attrs.iter().any(|a| *a != UserDataAttr::Flags && *a != UserDataAttr::Methods) {
+        return quote_spanned! {
+            item.span() => compile_error!("flags can only be combined with the Methods parameter");
+        };
+    }
+
+    let methods = attrs
+        .iter()
+        .filter_map(|a| a.get_code(quote!(Self)))
+        .collect();
+    let user_data = crate::flags::flags(mac, methods);
+    quote! {
+        #item
+
+        #user_data
+    }
+}
+
+#[cfg(not(feature = "bitflags"))]
+fn flags(item: &syn::Item, _attrs: &HashSet<UserDataAttr>) -> TokenStream2 {
+    quote_spanned! {
+        item.span() => compile_error!("flags requires the bitflags feature of rudeboy");
+    }
+}
+
+pub(crate) fn impl_user_data_attr_macro(
+    mut item: syn::Item,
+    attrs: Vec<&syn::NestedMeta>,
+) -> TokenStream2 {
+    let attrs = match attrs_to_user_data_attrs(attrs) {
+        Ok(uda) => uda,
+        Err(e) => return e,
+    };
+    if attrs.contains(&UserDataAttr::Flags) {
+        return flags(&item, &attrs);
+    }
+
+    let (name, generics) = if let syn::Item::Impl(i) = &item {
+        let self_ty = &i.self_ty;
+        (quote!(#self_ty), i.generics.clone())
+    } else if let syn::Item::Struct(s) = &item {
+        let name = &s.ident;
+        let (_, ty_generics, _) = s.generics.split_for_impl();
+        (quote!(#name #ty_generics), s.generics.clone())
+    } else if let syn::Item::Enum(e) = &item {
+        let name = &e.ident;
+        let (_, ty_generics, _) = e.generics.split_for_impl();
+        (quote!(#name #ty_generics), e.generics.clone())
+    } else {
+        return quote_spanned! {
+            item.span() => compile_error!("user_data macro can only be applied to a struct or an inherent impl block");
+        };
+    };
+
+    let rename_all = attrs.iter().find_map(|a| match a {
+        UserDataAttr::RenameAll(rule) => Some(rule),
+        _ => None,
+    });
+
+    let as_string = attrs.contains(&UserDataAttr::AsString);
+    let as_integer = attrs.contains(&UserDataAttr::AsInteger);
+    if as_string || as_integer {
+        let param = if as_string { "as_string" } else { "as_integer" };
+        let e = match &item {
+            syn::Item::Enum(e) => e,
+            _ => {
+                let msg = format!("{} can only be applied to enums without fields", param);
+                return quote_spanned! {
+                    item.span() => compile_error!(#msg);
+                }
+            }
+        };
+        if as_string && as_integer {
+            return quote_spanned! {
+                item.span() => compile_error!("An enum can't be converted to both strings and integers");
+            };
+        }
+        if attrs.contains(&UserDataAttr::Methods)
+            || attrs.contains(&UserDataAttr::MetaMethods)
+            || attrs.contains(&UserDataAttr::Constructors)
+            || attrs.contains(&UserDataAttr::Match)
+        {
+            let msg = format!("{} enums are converted to lua values rather than user data, so can't have methods, metamethods, constructors or match", param);
+            return quote_spanned! {
+                item.span() => compile_error!(#msg);
+            };
+        }
+
+        let conversions = if as_string {
+            repr::as_string(e, rename_all)
+        } else if let Some(rule) = rename_all {
+            return quote_spanned! {
+                rule.span() => compile_error!("rename_all can only be used with as_string");
+            };
+        } else {
+            repr::as_integer(e)
+        };
+        return quote! {
+            #item
+
+            #conversions
+        };
+    }
+
+    if let Some(rule) = rename_all {
+        return quote_spanned! {
+            rule.span() => compile_error!("rename_all can only be used with as_string");
+        };
+    }
+
+    let constructors = if attrs.contains(&UserDataAttr::Constructors) {
+        match &item {
+            syn::Item::Enum(e) if e.generics.lifetimes().next().is_some() => {
+                return quote_spanned! {
+                    e.generics.span() => compile_error!("Constructors can't be generated for enums with lifetimes, as their values can't be given to lua");
+                }
+            }
+            syn::Item::Enum(e) => Some(constructors::constructors(e)),
+            _ => {
+                return quote_spanned! {
+                    item.span() => compile_error!("Constructors can only be generated for enums");
+                }
+            }
+        }
+    } else {
+        None
+    };
+
+    let is_enum = matches!(item, syn::Item::Enum(_));
+    if attrs.contains(&UserDataAttr::Match) && !is_enum {
+        return quote_spanned! {
+            item.span() => compile_error!("Match can only be generated for enums");
+        };
+    }
+
+    // Enums get their variant helpers first, so that methods of the same name
+    // given by the user are registered later and take their place
+    let variant_code = match &item {
+        syn::Item::Enum(e) => {
+            let match_method = if attrs.contains(&UserDataAttr::Match) {
+                Some(variants::match_method(e))
+            } else {
+                None
+            };
+            let variant_methods = variants::variant_methods(e);
+            Some(quote!(#match_method #variant_methods))
+        }
+        _ => None,
+    };
+
+    // Metamethods are registered before methods, so that a method tagged
+    // #[meta(...)] replaces a generated metamethod, as rlua keeps the last one
+    let inner_code: Vec<_> = [UserDataAttr::MetaMethods, UserDataAttr::Methods]
+        .iter()
+        .filter(|a| attrs.contains(a))
+        .filter_map(|a| a.get_code(name.clone()))
+        .collect();
+
+    strip_field_attrs(&mut item);
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    quote! {
+        #item
+
+        impl #impl_generics ::rlua::UserData for #name #where_clause {
+            fn add_methods<'lua, M: ::rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+                #variant_code
+                #( #inner_code )*
+            }
+        }
+
+        #constructors
+    }
+}
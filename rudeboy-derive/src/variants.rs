@@ -0,0 +1,127 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+use crate::fields::FieldInfo;
+use crate::repr::rename;
+
+/// Registers a method on an enum which reads the value with `body`, an
+/// expression using `data: &Self`, and then evaluates `after` with the result
+/// bound to `__ret`. A type with lifetimes is `scoped`, and borrowed by rlua
+/// with `add_method`, as in the `methods` attribute.
+fn register(
+    name: &str,
+    scoped: bool,
+    arg: TokenStream2,
+    arg_ty: TokenStream2,
+    body: TokenStream2,
+    after: TokenStream2,
+) -> TokenStream2 {
+    if scoped {
+        quote! {
+            methods.add_method(#name, |ctx, data, #arg: #arg_ty| {
+                let __ret = #body;
+                #after
+            });
+        }
+    } else {
+        quote! {
+            methods.add_function(#name, |ctx, (__this, #arg): (::rlua::AnyUserData, #arg_ty)| {
+                let __ret = ::rudeboy::with_ref(&__this, |data: &Self| #body)?;
+                #after
+            });
+        }
+    }
+}
+
+/// Generates the `match` method of an enum, which calls the function in the
+/// given table named after the current variant, or `_`, with the variant's
+/// fields as arguments
+pub(crate) fn match_method(e: &syn::ItemEnum) -> TokenStream2 {
+    let mut arms = Vec::new();
+    for variant in &e.variants {
+        let fields = match FieldInfo::parse_all(&variant.fields) {
+            Ok(fields) => fields,
+            Err(e) => return e,
+        };
+        let name = &variant.ident;
+        let name_str = name.to_string();
+        let members = fields.iter().map(|f| &f.member);
+        let bindings: Vec<_> = fields.iter().map(|f| f.binding()).collect();
+        arms.push(quote! {
+            Self::#name { #( #members: #bindings, )* .. } => (
+                #name_str,
+                vec![#( ::rlua::ToLua::to_lua(#bindings.clone(), ctx) ),*],
+            ),
+        });
+    }
+
+    let type_name = e.ident.to_string();
+    let scoped = e.generics.lifetimes().next().is_some();
+    register(
+        "match",
+        scoped,
+        quote!(handlers),
+        quote!(::rlua::Table),
+        quote! {
+            match data {
+                #( #arms )*
+            }
+        },
+        quote! {
+            let (variant, args): (&str, ::std::vec::Vec<::rlua::Result<::rlua::Value>>) = __ret;
+            let args = args.into_iter().collect::<::rlua::Result<_>>()?;
+            ::rudeboy::__private::match_variant(#type_name, variant, handlers, args)
+        },
+    )
+}
+
+/// Generates the `is_<variant>` methods of an enum, with the variant names in
+/// snake case, and the `variant_name` and `variant_index` methods
+pub(crate) fn variant_methods(e: &syn::ItemEnum) -> TokenStream2 {
+    let scoped = e.generics.lifetimes().next().is_some();
+    let variants: Vec<_> = e.variants.iter().map(|v| &v.ident).collect();
+    let names: Vec<_> = variants.iter().map(|v| v.to_string()).collect();
+    let indices = 0..variants.len();
+
+    let predicates = variants.iter().zip(&names).map(|(variant, name)| {
+        let method = format!("is_{}", rename(name, "snake_case").unwrap());
+        register(
+            &method,
+            scoped,
+            quote!(()),
+            quote!(()),
+            quote!(matches!(data, Self::#variant { .. })),
+            quote!(Ok(__ret)),
+        )
+    });
+    let variant_name = register(
+        "variant_name",
+        scoped,
+        quote!(()),
+        quote!(()),
+        quote! {
+            match data {
+                #( Self::#variants { .. } => #names, )*
+            }
+        },
+        quote!(Ok(__ret)),
+    );
+    let variant_index = register(
+        "variant_index",
+        scoped,
+        quote!(()),
+        quote!(()),
+        quote! {
+            match data {
+                #( Self::#variants { .. } => #indices, )*
+            }
+        },
+        quote!(Ok(__ret)),
+    );
+
+    quote! {
+        #( #predicates )*
+        #variant_name
+        #variant_index
+    }
+}
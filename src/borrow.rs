@@ -0,0 +1,173 @@
+use rlua::{AnyUserData, Error, Result, UserData};
+
+use crate::forward::Forward;
+use crate::tombstone;
+use crate::{FieldProxy, Shared};
+
+/// Calls the given function with a reference to the value inside a lua user
+/// data, without cloning it out of lua. The user data may also be a
+/// [`Shared`] holding a `T`, which is read locked for the duration of the
+/// call, or a [`FieldProxy`] for a `T`, in which case the field is borrowed
+/// from its parent. Fails if the user data is not of type `T`, is currently
+/// mutably borrowed, or its value was moved out by [`take`].
+///
+/// # Examples
+/// ```
+/// # fn test() -> rlua::Result<()> {
+/// use rudeboy::user_data;
+///
+/// // Note that Big does not implement Clone
+/// #[user_data]
+/// struct Big {
+///     data: Vec<u8>,
+/// }
+///
+/// let lua = rlua::Lua::new();
+/// lua.context(|ctx| {
+///     ctx.globals().set("big", Big { data: vec![0; 4096] })?;
+///
+///     let big = ctx.load("big").eval::<rlua::AnyUserData>()?;
+///     let len = rudeboy::with_ref::<Big, _>(&big, |big| big.data.len())?;
+///     assert_eq!(len, 4096);
+///
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+///
+/// [`Shared`]: struct.Shared.html
+/// [`FieldProxy`]: struct.FieldProxy.html
+/// [`take`]: fn.take.html
+pub fn with_ref<'lua, T, R>(value: &AnyUserData<'lua>, f: impl FnOnce(&T) -> R) -> Result<R>
+where
+    T: 'static + UserData,
+{
+    tombstone::check(value)?;
+    match value.borrow::<T>() {
+        Ok(value) => Ok(f(&*value)),
+        Err(Error::UserDataTypeMismatch) if value.is::<Shared<T>>() => {
+            Shared::with_ref(value, f)
+        }
+        Err(Error::UserDataTypeMismatch) if value.is::<FieldProxy<T>>() => {
+            FieldProxy::with_ref(value, f)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Calls the given function with a mutable reference to the value inside a
+/// lua user data, a [`Shared`] holding a `T`, or the parent of a [`FieldProxy`]
+/// for a `T`. Fails if the user data is not of type `T`, is currently
+/// borrowed, or its value was moved out by [`take`].
+///
+/// [`Shared`]: struct.Shared.html
+/// [`FieldProxy`]: struct.FieldProxy.html
+/// [`take`]: fn.take.html
+pub fn with_mut<'lua, T, R>(value: &AnyUserData<'lua>, f: impl FnOnce(&mut T) -> R) -> Result<R>
+where
+    T: 'static + UserData,
+{
+    tombstone::check(value)?;
+    match value.borrow_mut::<T>() {
+        Ok(mut value) => Ok(f(&mut *value)),
+        Err(Error::UserDataTypeMismatch) if value.is::<Shared<T>>() => {
+            Shared::with_mut(value, f)
+        }
+        Err(Error::UserDataTypeMismatch) if value.is::<FieldProxy<T>>() => {
+            FieldProxy::with_mut(value, f)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Moves the value out of a lua user data without cloning it, leaving
+/// `T::default()` in its place. The user data is marked as moved, so any later
+/// use of it from lua through the code generated by this crate, or through
+/// [`with_ref`] and [`with_mut`], raises a "value moved" error. Fails if the
+/// user data is not of type `T`, is currently borrowed, or was already moved.
+///
+/// Only a plain `T` can be taken, not a [`Shared`] or [`FieldProxy`], since
+/// the value is also reachable through those from elsewhere.
+///
+/// # Examples
+/// ```
+/// # fn test() -> rlua::Result<()> {
+/// use rudeboy::user_data;
+///
+/// // Note that Big does not implement Clone
+/// #[user_data]
+/// #[derive(Default)]
+/// struct Big {
+///     data: Vec<u8>,
+/// }
+///
+/// let lua = rlua::Lua::new();
+/// lua.context(|ctx| {
+///     ctx.globals().set("big", Big { data: vec![0; 4096] })?;
+///
+///     let big = ctx.load("big").eval::<rlua::AnyUserData>()?;
+///     let big = rudeboy::take::<Big>(&big)?;
+///     assert_eq!(big.data.len(), 4096);
+///
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+///
+/// [`with_ref`]: fn.with_ref.html
+/// [`with_mut`]: fn.with_mut.html
+/// [`Shared`]: struct.Shared.html
+/// [`FieldProxy`]: struct.FieldProxy.html
+pub fn take<'lua, T>(value: &AnyUserData<'lua>) -> Result<T>
+where
+    T: 'static + UserData + Default,
+{
+    take_as(value, "value moved")
+}
+
+/// Moves the value out of a lua user data for a method generated by the
+/// [`methods`] attribute which takes `self` by value. Later uses of the user
+/// data raise an "already consumed" error.
+///
+/// [`methods`]: ../attr.methods.html
+pub fn consume<'lua, T>(value: &AnyUserData<'lua>) -> Result<T>
+where
+    T: 'static + UserData + Default,
+{
+    take_as(value, "already consumed")
+}
+
+/// Moves the value out of a user data as with [`take`], marking it with the
+/// given error message
+///
+/// [`take`]: fn.take.html
+fn take_as<'lua, T>(value: &AnyUserData<'lua>, message: &'static str) -> Result<T>
+where
+    T: 'static + UserData + Default,
+{
+    tombstone::check(value)?;
+    let taken = std::mem::take(&mut *value.borrow_mut::<T>()?);
+    tombstone::bury(value, message)?;
+    Ok(taken)
+}
+
+/// Calls the given function with references to the values inside two user
+/// data, as with [`with_ref`]. Both may hold the same [`Shared`] value, as
+/// its read lock can be held more than once.
+///
+/// [`with_ref`]: fn.with_ref.html
+/// [`Shared`]: struct.Shared.html
+pub(crate) fn with_refs<'lua, T, R>(
+    lhs: &AnyUserData<'lua>,
+    rhs: &AnyUserData<'lua>,
+    f: impl FnOnce(&T, &T) -> R,
+) -> Result<R>
+where
+    T: 'static + UserData,
+{
+    with_ref(lhs, |lhs| with_ref(rhs, |rhs| f(lhs, rhs)))?
+}
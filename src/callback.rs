@@ -0,0 +1,89 @@
+use std::marker::PhantomData;
+
+use rlua::{Context, FromLua, FromLuaMulti, Function, RegistryKey, Result, ToLuaMulti, Value};
+
+/// A lua function stored in the lua registry, so that it can be kept in a
+/// struct field and called later from rust with typed arguments and return
+/// values. `Args` is the type passed to the function and `Ret` is the type it
+/// returns, using the same conversions as `rlua::Function::call`.
+///
+/// Since it implements `rlua::FromLua`, a `LuaCallback` can be taken directly
+/// as a parameter of a method exported with the [`methods`] attribute.
+///
+/// Note that the function is only removed from the registry once the
+/// callback is dropped and `rlua::Context::expire_registry_values` is called.
+///
+/// # Examples
+/// ```
+/// # fn test() -> rlua::Result<()> {
+/// use rudeboy::{methods, user_data, LuaCallback};
+///
+/// #[user_data(Methods)]
+/// struct Timer {
+///     on_fire: Option<LuaCallback<u32, ()>>,
+/// }
+///
+/// #[methods]
+/// impl Timer {
+///     pub fn on_fire(&mut self, callback: LuaCallback<u32, ()>) {
+///         self.on_fire = Some(callback);
+///     }
+/// }
+///
+/// let lua = rlua::Lua::new();
+/// lua.context(|ctx| {
+///     let globals = ctx.globals();
+///     globals.set("timer", Timer { on_fire: None })?;
+///     ctx.load("timer:on_fire(function(n) fired = n end)").exec()?;
+///
+///     let timer = globals.get::<_, rlua::AnyUserData>("timer")?;
+///     if let Some(callback) = &timer.borrow::<Timer>()?.on_fire {
+///         callback.call(ctx, 23)?;
+///     }
+///     assert_eq!(globals.get::<_, u32>("fired")?, 23);
+///
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+///
+/// [`methods`]: attr.methods.html
+pub struct LuaCallback<Args, Ret> {
+    key: RegistryKey,
+    _signature: PhantomData<fn(Args) -> Ret>,
+}
+
+impl<Args, Ret> LuaCallback<Args, Ret> {
+    /// Stores the given function in the registry of the given context
+    pub fn new<'lua>(ctx: Context<'lua>, function: Function<'lua>) -> Result<Self> {
+        Ok(LuaCallback {
+            key: ctx.create_registry_value(function)?,
+            _signature: PhantomData,
+        })
+    }
+
+    /// Retrieves the stored function from the registry. Fails if the context
+    /// belongs to a different `rlua::Lua` instance than the one the callback
+    /// was created in
+    pub fn function<'lua>(&self, ctx: Context<'lua>) -> Result<Function<'lua>> {
+        ctx.registry_value(&self.key)
+    }
+
+    /// Calls the stored function with the given arguments
+    pub fn call<'lua>(&self, ctx: Context<'lua>, args: Args) -> Result<Ret>
+    where
+        Args: ToLuaMulti<'lua>,
+        Ret: FromLuaMulti<'lua>,
+    {
+        self.function(ctx)?.call(args)
+    }
+}
+
+impl<'lua, Args, Ret> FromLua<'lua> for LuaCallback<Args, Ret> {
+    fn from_lua(value: Value<'lua>, ctx: Context<'lua>) -> Result<Self> {
+        let function = Function::from_lua(value, ctx)?;
+        LuaCallback::new(ctx, function)
+    }
+}
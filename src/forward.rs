@@ -0,0 +1,123 @@
+use std::marker::PhantomData;
+
+use rlua::{
+    AnyUserData, Context, FromLuaMulti, MetaMethod, Result, ToLuaMulti, UserData,
+    UserDataMethods,
+};
+
+/// A user data type that stands in for a value of type `T` stored elsewhere,
+/// and can lend it out given its own user data
+pub(crate) trait Forward<T>: 'static + UserData {
+    fn with_ref<'lua, R>(this: &AnyUserData<'lua>, f: impl FnOnce(&T) -> R) -> Result<R>;
+    fn with_mut<'lua, R>(this: &AnyUserData<'lua>, f: impl FnOnce(&mut T) -> R) -> Result<R>;
+}
+
+/// Registers the methods of `T` on a [`Forward`] implementor `U`, so that
+/// `T::add_methods` can be reused as is. Methods and metamethods taking
+/// `&T`/`&mut T` are registered as functions taking the `U` user data, and
+/// borrow through it; functions are registered unchanged, since their
+/// arguments will be a `U` rather than a `T`.
+pub(crate) struct Forwarder<'m, M, U> {
+    methods: &'m mut M,
+    _target: PhantomData<U>,
+}
+
+impl<'m, M, U> Forwarder<'m, M, U> {
+    pub(crate) fn new(methods: &'m mut M) -> Self {
+        Forwarder {
+            methods,
+            _target: PhantomData,
+        }
+    }
+}
+
+impl<'lua, 'm, T, U, M> UserDataMethods<'lua, T> for Forwarder<'m, M, U>
+where
+    T: UserData,
+    U: Forward<T>,
+    M: UserDataMethods<'lua, U>,
+{
+    fn add_method<S, A, R, F>(&mut self, name: &S, method: F)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + Fn(Context<'lua>, &T, A) -> Result<R>,
+    {
+        self.methods.add_function(name, move |ctx, (this, args): (AnyUserData<'lua>, A)| {
+            U::with_ref(&this, |inner| method(ctx, inner, args))?
+        });
+    }
+
+    fn add_method_mut<S, A, R, F>(&mut self, name: &S, mut method: F)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + FnMut(Context<'lua>, &mut T, A) -> Result<R>,
+    {
+        self.methods.add_function_mut(name, move |ctx, (this, args): (AnyUserData<'lua>, A)| {
+            U::with_mut(&this, |inner| method(ctx, inner, args))?
+        });
+    }
+
+    fn add_function<S, A, R, F>(&mut self, name: &S, function: F)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + Fn(Context<'lua>, A) -> Result<R>,
+    {
+        self.methods.add_function(name, function);
+    }
+
+    fn add_function_mut<S, A, R, F>(&mut self, name: &S, function: F)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + FnMut(Context<'lua>, A) -> Result<R>,
+    {
+        self.methods.add_function_mut(name, function);
+    }
+
+    fn add_meta_method<A, R, F>(&mut self, meta: MetaMethod, method: F)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + Fn(Context<'lua>, &T, A) -> Result<R>,
+    {
+        self.methods.add_meta_function(meta, move |ctx, (this, args): (AnyUserData<'lua>, A)| {
+            U::with_ref(&this, |inner| method(ctx, inner, args))?
+        });
+    }
+
+    fn add_meta_method_mut<A, R, F>(&mut self, meta: MetaMethod, mut method: F)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + FnMut(Context<'lua>, &mut T, A) -> Result<R>,
+    {
+        self.methods.add_meta_function_mut(meta, move |ctx, (this, args): (AnyUserData<'lua>, A)| {
+            U::with_mut(&this, |inner| method(ctx, inner, args))?
+        });
+    }
+
+    fn add_meta_function<A, R, F>(&mut self, meta: MetaMethod, function: F)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + Fn(Context<'lua>, A) -> Result<R>,
+    {
+        self.methods.add_meta_function(meta, function);
+    }
+
+    fn add_meta_function_mut<A, R, F>(&mut self, meta: MetaMethod, function: F)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + FnMut(Context<'lua>, A) -> Result<R>,
+    {
+        self.methods.add_meta_function_mut(meta, function);
+    }
+}
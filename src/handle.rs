@@ -0,0 +1,85 @@
+use std::marker::PhantomData;
+
+use rlua::{AnyUserData, Context, RegistryKey, Result, UserData};
+
+/// A persistent, typed reference to a user data value owned by lua. The value
+/// is kept alive in the lua registry, so the handle can be stored outside of
+/// `rlua::Lua::context` and used for typed access in later contexts.
+///
+/// Using a handle with a context from a different `rlua::Lua` instance than
+/// the one it was created in returns `rlua::Error::MismatchedRegistryKey`.
+///
+/// # Examples
+/// ```
+/// # fn test() -> rlua::Result<()> {
+/// use rudeboy::{methods, user_data, Handle};
+///
+/// #[user_data(Methods)]
+/// struct Entity {
+///     hp: i32,
+/// }
+///
+/// #[methods]
+/// impl Entity {
+///     pub fn damage(&mut self, amount: i32) {
+///         self.hp -= amount;
+///     }
+/// }
+///
+/// let lua = rlua::Lua::new();
+/// let player = lua.context(|ctx| {
+///     ctx.globals().set("player", Entity { hp: 10 })?;
+///     Handle::<Entity>::new(ctx, ctx.load("player").eval()?)
+/// })?;
+///
+/// lua.context(|ctx| {
+///     ctx.load("player:damage(3)").exec()?;
+///     assert_eq!(player.borrow(ctx, |player| player.hp)?, 7);
+///
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+pub struct Handle<T> {
+    key: RegistryKey,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static + UserData> Handle<T> {
+    /// Creates a handle to the given user data. Fails if it is not of type `T`
+    pub fn new<'lua>(ctx: Context<'lua>, value: AnyUserData<'lua>) -> Result<Self> {
+        crate::with_ref::<T, _>(&value, |_| ())?;
+
+        Ok(Handle {
+            key: ctx.create_registry_value(value)?,
+            _type: PhantomData,
+        })
+    }
+
+    /// Retrieves the user data the handle refers to
+    pub fn user_data<'lua>(&self, ctx: Context<'lua>) -> Result<AnyUserData<'lua>> {
+        ctx.registry_value(&self.key)
+    }
+
+    /// Calls the given function with a reference to the value the handle
+    /// refers to. See [`with_ref`]
+    ///
+    /// [`with_ref`]: fn.with_ref.html
+    pub fn borrow<'lua, R>(&self, ctx: Context<'lua>, f: impl FnOnce(&T) -> R) -> Result<R> {
+        crate::with_ref(&self.user_data(ctx)?, f)
+    }
+
+    /// Calls the given function with a mutable reference to the value the
+    /// handle refers to. See [`with_mut`]
+    ///
+    /// [`with_mut`]: fn.with_mut.html
+    pub fn borrow_mut<'lua, R>(
+        &self,
+        ctx: Context<'lua>,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Result<R> {
+        crate::with_mut(&self.user_data(ctx)?, f)
+    }
+}
@@ -0,0 +1,194 @@
+use rlua::{AnyUserData, Context, Function, MultiValue, Result, Table, ToLuaMulti, UserData, Value};
+
+/// Turns a rust iterator into a stateful lua iterator function, for use with
+/// lua's generic `for` loop. Items are converted one per call, and iterators of
+/// tuples produce multiple loop variables. Once the rust iterator is
+/// exhausted, the function returns nothing, ending the loop.
+///
+/// The iterator is kept by the lua function, so it must be `'static` and can't
+/// borrow from a user data. Iterating over a collection inside a user data
+/// this way means cloning it, or collecting what's needed, up front; use
+/// [`cursor`] to read one item at a time instead.
+///
+/// Note that lua also ends the loop when the first value returned is `nil`,
+/// so iteration stops early at an item whose first value converts to `nil`,
+/// such as `None` or a tuple beginning with `None`.
+///
+/// Methods exported with the [`methods`] attribute can be marked
+/// `#[lua(iter)]` to have their return value passed through this function.
+///
+/// # Examples
+/// ```
+/// # fn test() -> rlua::Result<()> {
+/// struct Inventory {
+///     items: Vec<(String, u32)>,
+/// }
+///
+/// impl rlua::UserData for Inventory {
+///     fn add_methods<'lua, M: rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+///         methods.add_method("items", |ctx, data, ()| {
+///             rudeboy::iter(ctx, data.items.clone())
+///         });
+///     }
+/// }
+///
+/// let lua = rlua::Lua::new();
+/// lua.context(|ctx| {
+///     let items = vec![("apple".to_string(), 2), ("pear".to_string(), 3)];
+///     ctx.globals().set("inventory", Inventory { items })?;
+///
+///     let total = ctx.load(r#"
+///         local total = 0
+///         for name, count in inventory:items() do
+///             total = total + count
+///         end
+///         return total
+///     "#).eval::<u32>()?;
+///     assert_eq!(total, 5);
+///
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+///
+/// [`methods`]: attr.methods.html
+/// [`cursor`]: fn.cursor.html
+pub fn iter<'lua, I>(ctx: Context<'lua>, iter: I) -> Result<Function<'lua>>
+where
+    I: IntoIterator,
+    I::IntoIter: 'static + Send,
+    I::Item: for<'callback> ToLuaMulti<'callback>,
+{
+    let mut iter = iter.into_iter();
+    ctx.create_function_mut(move |ctx, ()| match iter.next() {
+        Some(item) => item.to_lua_multi(ctx),
+        None => Ok(MultiValue::new()),
+    })
+}
+
+/// Turns a user data into a stateful lua iterator function, for use with lua's
+/// generic `for` loop, which reads one item at a time. Each call borrows the
+/// value inside the user data with [`with_ref`], and passes it to `next` along
+/// with the number of items read so far. Iteration ends at the first `None`.
+///
+/// Unlike [`iter`], nothing is copied out of the user data up front, so only
+/// the items themselves need to be owned, and changes made to the value while
+/// the loop runs are seen by it. The function keeps the user data alive.
+///
+/// Methods exported with the [`methods`] attribute can be marked
+/// `#[lua(cursor)]` to be called this way.
+///
+/// # Examples
+/// ```
+/// # fn test() -> rlua::Result<()> {
+/// struct Inventory {
+///     items: Vec<(String, u32)>,
+/// }
+///
+/// impl rlua::UserData for Inventory {
+///     fn add_methods<'lua, M: rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+///         methods.add_function("items", |ctx, this: rlua::AnyUserData| {
+///             rudeboy::cursor(ctx, this, |inventory: &Inventory, index| {
+///                 inventory.items.get(index).map(|(_, count)| *count)
+///             })
+///         });
+///     }
+/// }
+///
+/// let lua = rlua::Lua::new();
+/// lua.context(|ctx| {
+///     let items = vec![("apple".to_string(), 2), ("pear".to_string(), 3)];
+///     ctx.globals().set("inventory", Inventory { items })?;
+///
+///     let total = ctx.load(r#"
+///         local total = 0
+///         for count in inventory:items() do
+///             total = total + count
+///         end
+///         return total
+///     "#).eval::<u32>()?;
+///     assert_eq!(total, 5);
+///
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+///
+/// [`with_ref`]: fn.with_ref.html
+/// [`iter`]: fn.iter.html
+/// [`methods`]: attr.methods.html
+pub fn cursor<'lua, T, R, F>(ctx: Context<'lua>, value: AnyUserData<'lua>, next: F) -> Result<Function<'lua>>
+where
+    T: 'static + UserData,
+    R: for<'callback> ToLuaMulti<'callback>,
+    F: 'static + Send + Fn(&T, usize) -> Option<R>,
+{
+    cursor_lua(ctx, value, move |ctx, data, index| match next(data, index) {
+        Some(item) => item.to_lua_multi(ctx).map(Some),
+        None => Ok(None),
+    })
+}
+
+/// Builds a [`cursor`] from a function which converts each item to lua itself,
+/// while the value is still borrowed, so that the items may borrow from it
+///
+/// [`cursor`]: fn.cursor.html
+pub fn cursor_lua<'lua, T, F>(ctx: Context<'lua>, value: AnyUserData<'lua>, next: F) -> Result<Function<'lua>>
+where
+    T: 'static + UserData,
+    F: 'static + Send + for<'callback> Fn(Context<'callback>, &T, usize) -> Result<Option<MultiValue<'callback>>>,
+{
+    // The function can't hold the user data itself, so it's kept alive through
+    // the registry
+    let value = ctx.create_registry_value(value)?;
+    let mut index = 0;
+    ctx.create_function_mut(move |ctx, ()| {
+        let value = ctx.registry_value::<AnyUserData>(&value)?;
+        match crate::with_ref(&value, |data| next(ctx, data, index))?? {
+            Some(items) => {
+                index += 1;
+                Ok(items)
+            }
+            None => Ok(MultiValue::new()),
+        }
+    })
+}
+
+/// Returns the iterator function, state and initial control value for the
+/// pairs metamethod, which visit the given entries in order. The entries are
+/// copied into a lua table, so the iterator outlives the borrow they were read
+/// from.
+pub fn pairs<'lua>(
+    ctx: Context<'lua>,
+    entries: Vec<(Value<'lua>, Value<'lua>)>,
+) -> Result<(Function<'lua>, Table<'lua>, Value<'lua>)> {
+    let keys = ctx.create_table()?;
+    let values = ctx.create_table()?;
+    let positions = ctx.create_table()?;
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        keys.raw_set(i + 1, key.clone())?;
+        values.raw_set(i + 1, value)?;
+        positions.raw_set(key, i + 1)?;
+    }
+    let state = ctx.create_sequence_from(vec![keys, values, positions])?;
+
+    // The control value passed back in is the previous key, or nil to start
+    let next = ctx.create_function(|_, (state, key): (Table, Value)| {
+        let (keys, values, positions): (Table, Table, Table) =
+            (state.raw_get(1)?, state.raw_get(2)?, state.raw_get(3)?);
+        let position = match key {
+            Value::Nil => 1,
+            key => match positions.raw_get::<_, Option<usize>>(key)? {
+                Some(position) => position + 1,
+                None => return Ok((Value::Nil, Value::Nil)),
+            },
+        };
+        Ok((keys.raw_get(position)?, values.raw_get(position)?))
+    })?;
+
+    Ok((next, state, Value::Nil))
+}
@@ -52,8 +52,10 @@
 //! # Exporting methods
 //! To export methods for a struct or enum, use the [`methods`] attribute on the
 //! impl block for the corresponding type, containing the methods that you wish
-//! to export to lua. Currently, there is no way to ignore a method other than
-//! placing it in a separate impl block without the [`methods`] attribute.
+//! to export to lua. A method can be left out by marking it `#[lua(skip)]`,
+//! or by placing it in a separate impl block without the [`methods`]
+//! attribute. Several methods can be exported under a single lua name with
+//! `#[lua(overload = "name")]`; see the [`methods`] attribute documentation.
 //!
 //! ## Examples
 //! ```
@@ -76,11 +78,9 @@
 //!             Sign::Plus => Sign::Minus,
 //!         }
 //!     }
-//! }
 //!
-//! 
-//! impl Sign {
 //!     // ... but this method won't
+//!     #[lua(skip)]
 //!     pub fn apply(&self, x: i32) -> i32 {
 //!         match self {
 //!             Sign::Minus => -x,
@@ -126,7 +126,9 @@
 //! use rudeboy::{metamethods, methods, user_data};
 //!
 //! // Tagging a type with user_data with no parameters means it can be passed
-//! // in and out of lua, but no metamethods or methods will be available
+//! // in and out of lua, but no metamethods or methods will be available, other
+//! // than the is_single, is_double, variant_name and variant_index methods
+//! // generated for enums
 //! #[user_data]
 //! #[derive(Clone)]
 //! enum Amount {
@@ -167,18 +169,132 @@
 //! # assert!(test().is_ok());
 //! ```
 //!
+//! ## Enums as lua values
+//! Enums without fields can instead be converted to and from plain lua values,
+//! by giving [`user_data`] the `as_string` parameter. Each variant becomes a
+//! string of its name, optionally renamed with `rename_all`, so that scripts
+//! can pass `"minus"` rather than looking up a global. With `as_integer`, each
+//! variant becomes its discriminant, and [`RudeboyTable`] gives a table of
+//! them by name.
+//!
+//! ```
+//! # fn test() -> rlua::Result<()> {
+//! use rudeboy::user_data;
+//!
+//! #[user_data(as_string, rename_all = "lowercase")]
+//! #[derive(PartialEq, Debug)]
+//! enum Sign {
+//!     Minus,
+//!     Plus,
+//! }
+//!
+//! let lua = rlua::Lua::new();
+//! lua.context(|ctx| {
+//!     ctx.globals().set("sign", Sign::Minus)?;
+//!     assert!(ctx.load("sign == 'minus'").eval::<bool>()?);
+//!     assert_eq!(ctx.load("'plus'").eval::<Sign>()?, Sign::Plus);
+//!     assert!(ctx.load("'zero'").eval::<Sign>().is_err());
+//!
+//!     Ok(())
+//! })?;
+//! # Ok(())
+//! # }
+//! # assert!(test().is_ok());
+//! ```
+//!
+//! ## Flags
+//! With the `bitflags` feature, [`user_data`] can be placed on a `bitflags!`
+//! invocation with the `flags` parameter, giving the flags type bitwise
+//! operators, `contains`, `insert` and `remove` methods, a `tostring` listing
+//! the set flags and a [`RudeboyTable`] of the flags by name. The `bitflags!`
+//! macro is re-exported by this crate under the same feature.
+//!
+// The example can only be compiled and run with the feature enabled
+#![cfg_attr(feature = "bitflags", doc = "```")]
+#![cfg_attr(not(feature = "bitflags"), doc = "```ignore")]
+//! use rudeboy::{bitflags, user_data, RudeboyTable};
+//!
+//! #[user_data(flags)]
+//! bitflags! {
+//!     struct Perms: u32 {
+//!         const READ = 0b01;
+//!         const WRITE = 0b10;
+//!     }
+//! }
+//!
+//! # fn main() -> rlua::Result<()> {
+//! let lua = rlua::Lua::new();
+//! lua.context(|ctx| {
+//!     let globals = ctx.globals();
+//!     globals.set("Perms", Perms::generate_table(ctx)?)?;
+//!     globals.set("perms", Perms::READ)?;
+//!     ctx.load("perms:insert(Perms.WRITE)").exec()?;
+//!
+//!     assert_eq!(globals.get::<_, Perms>("perms")?, Perms::READ | Perms::WRITE);
+//!     assert_eq!(ctx.load("tostring(perms & Perms.READ)").eval::<String>()?, "READ");
+//!
+//!     Ok(())
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! [`metamethods`]: attr.metamethods.html
 //! [`methods`]: attr.methods.html
 //! [`user_data`]: attr.user_data.html
 //! [`RudeboyMetaMethods`]: trait.RudeboyMetaMethods.html
 //! [`RudeboyMethods`]: trait.RudeboyMethods.html
+//! [`RudeboyTable`]: trait.RudeboyTable.html
 pub use rudeboy_derive::{
     metamethods,
     methods,
     user_data,
 };
 
-use rlua::{UserData, UserDataMethods};
+mod borrow;
+mod callback;
+mod forward;
+mod handle;
+mod iterator;
+mod ops;
+mod overload;
+mod probe;
+mod proxy;
+mod scoped;
+mod sequence;
+mod shared;
+mod tombstone;
+mod variant;
+
+#[cfg(feature = "bitflags")]
+pub use bitflags::bitflags;
+pub use borrow::{take, with_mut, with_ref};
+pub use callback::LuaCallback;
+pub use handle::Handle;
+pub use iterator::{cursor, iter};
+pub use proxy::{FieldProxy, MapProxy, ProxyMap};
+pub use scoped::scoped;
+pub use sequence::SequenceProxy;
+pub use shared::Shared;
+
+use rlua::{Context, Table, UserData, UserDataMethods};
+
+/// Used by the generated code. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::borrow::consume;
+    pub use crate::iterator::{cursor_lua, pairs};
+    pub use crate::ops::{binary, binary_ref, operand, unary, unsupported_operands};
+    pub use crate::probe::*;
+    pub use crate::overload::no_overload;
+    pub use crate::sequence::{
+        sequence_get, sequence_key, sequence_len, sequence_methods, sequence_set,
+    };
+    pub use crate::variant::{
+        constructor_arg, constructor_args, constructor_fields, match_variant, no_variant,
+        unknown_variant,
+    };
+}
 
 /// Provides methods for registering each supported metamethod. The
 /// `generate_metamethods` method will call all of them. Generated by the
@@ -190,6 +306,22 @@ pub trait RudeboyMetaMethods : Sized + UserData {
     /// `instance.field`
     fn generate_index<'lua, M: UserDataMethods<'lua, Self>>(_methods: &mut M) {}
 
+    /// The new index metamethod for assigning to fields using the dot or
+    /// bracket syntax: `instance.field = value`, `instance[key] = value`
+    fn generate_new_index<'lua, M: UserDataMethods<'lua, Self>>(_methods: &mut M) {}
+
+    /// The pairs metamethod for iterating over fields using the generic for
+    /// syntax: `for key, value in pairs(instance)`
+    fn generate_pairs<'lua, M: UserDataMethods<'lua, Self>>(_methods: &mut M) {}
+
+    /// The length metamethod for the unary `#` operator
+    fn generate_len<'lua, M: UserDataMethods<'lua, Self>>(_methods: &mut M) {}
+
+    /// The `push`, `pop` and `insert` methods of a struct behaving as a lua
+    /// array over a `#[lua(sequence)]` field. These aren't metamethods, but
+    /// are generated alongside the sequence's index and length metamethods
+    fn generate_sequence_methods<'lua, M: UserDataMethods<'lua, Self>>(_methods: &mut M) {}
+
     /// The equality metamethod for the binary `==` operator
     fn generate_eq<'lua, M: UserDataMethods<'lua, Self>>(_methods: &mut M) {}
 
@@ -238,6 +370,10 @@ pub trait RudeboyMetaMethods : Sized + UserData {
     /// Calls every individual `generate_*` method in this trait
     fn generate_metamethods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
         Self::generate_index(methods);
+        Self::generate_new_index(methods);
+        Self::generate_pairs(methods);
+        Self::generate_len(methods);
+        Self::generate_sequence_methods(methods);
         Self::generate_eq(methods);
         Self::generate_add(methods);
         Self::generate_sub(methods);
@@ -256,6 +392,17 @@ pub trait RudeboyMetaMethods : Sized + UserData {
     }
 }
 
+/// Provides a table for a type, holding named values for use by scripts, to be
+/// set as a lua global. Generated by the [`user_data`] attribute for
+/// `as_integer` enums, whose table maps each variant's name to its
+/// discriminant, for enums given the `Constructors` parameter, whose table
+/// creates each variant, and for flags types, whose table holds each flag.
+///
+/// [`user_data`]: attr.user_data.html
+pub trait RudeboyTable {
+    fn generate_table<'lua>(ctx: Context<'lua>) -> rlua::Result<Table<'lua>>;
+}
+
 /// Used to expose, to rlua, rust methods for a UserData struct
 ///
 /// Implementations provided by [`methods`]
@@ -0,0 +1,82 @@
+//! Registration of operator metamethods, used by the code generated by the
+//! [`metamethods`] attribute. Operands are received as user data and borrowed
+//! through [`with_ref`], so they may be a plain `T`, a [`Shared`] or a
+//! [`FieldProxy`].
+//!
+//! [`metamethods`]: ../attr.metamethods.html
+//! [`with_ref`]: ../fn.with_ref.html
+//! [`Shared`]: ../struct.Shared.html
+//! [`FieldProxy`]: ../struct.FieldProxy.html
+use rlua::{
+    AnyUserData, Error, MetaMethod, Result, ToLuaMulti, UserData, UserDataMethods, Value,
+};
+
+use crate::borrow::with_refs;
+use crate::overload::type_name;
+use crate::with_ref;
+
+/// Registers a binary operator taking both operands by value. Each operand is
+/// cloned out of its user data in turn.
+pub fn binary<'lua, T, R, M>(methods: &mut M, meta: MetaMethod, op: fn(T, T) -> R)
+where
+    T: 'static + UserData + Clone,
+    R: 'static + ToLuaMulti<'lua>,
+    M: UserDataMethods<'lua, T>,
+{
+    methods.add_meta_function(meta, move |_, (lhs, rhs): (AnyUserData, AnyUserData)| {
+        let lhs = with_ref(&lhs, T::clone)?;
+        let rhs = with_ref(&rhs, T::clone)?;
+        Ok(op(lhs, rhs))
+    });
+}
+
+/// Registers a binary operator taking both operands by reference
+pub fn binary_ref<'lua, T, R, M>(methods: &mut M, meta: MetaMethod, op: fn(&T, &T) -> R)
+where
+    T: 'static + UserData,
+    R: 'static + ToLuaMulti<'lua>,
+    M: UserDataMethods<'lua, T>,
+{
+    methods.add_meta_function(meta, move |_, (lhs, rhs): (AnyUserData, AnyUserData)| {
+        with_refs(&lhs, &rhs, op)
+    });
+}
+
+/// Registers a unary operator taking its operand by value
+pub fn unary<'lua, T, R, M>(methods: &mut M, meta: MetaMethod, op: fn(T) -> R)
+where
+    T: 'static + UserData + Clone,
+    R: 'static + ToLuaMulti<'lua>,
+    M: UserDataMethods<'lua, T>,
+{
+    methods.add_meta_function(meta, move |_, this: AnyUserData| {
+        Ok(op(with_ref(&this, T::clone)?))
+    });
+}
+
+/// Clones a `T` out of an operand of a metamethod taking operands of several
+/// types, giving `None` if the operand is not a user data holding a `T`
+pub fn operand<T>(value: &Value) -> Result<Option<T>>
+where
+    T: 'static + UserData + Clone,
+{
+    match value {
+        Value::UserData(value) => match with_ref(value, T::clone) {
+            Ok(value) => Ok(Some(value)),
+            Err(Error::UserDataTypeMismatch) => Ok(None),
+            Err(err) => Err(err),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// The error raised when a metamethod taking operands of several types is
+/// called with operands it doesn't accept
+pub fn unsupported_operands(metamethod: &str, lhs: &Value, rhs: &Value) -> Error {
+    Error::RuntimeError(format!(
+        "unsupported operand types for {}: {} and {}",
+        metamethod,
+        type_name(lhs),
+        type_name(rhs),
+    ))
+}
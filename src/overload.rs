@@ -0,0 +1,33 @@
+//! Support for the dispatchers generated by the [`methods`] attribute for
+//! overloaded methods.
+//!
+//! [`methods`]: ../attr.methods.html
+use rlua::{Error, MultiValue, Value};
+
+/// The name of the lua type of a value, as reported by lua's `type` function
+pub(crate) fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Nil => "nil",
+        Value::Boolean(_) => "boolean",
+        Value::LightUserData(_) | Value::UserData(_) => "userdata",
+        Value::Integer(_) | Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Table(_) => "table",
+        Value::Function(_) => "function",
+        Value::Thread(_) => "thread",
+        Value::Error(_) => "error",
+    }
+}
+
+/// The error raised when none of the overloads of a method accept the
+/// arguments it was called with. The first argument, the user data the method
+/// was called on, is not listed.
+pub fn no_overload(name: &str, candidates: &[&str], args: &MultiValue) -> Error {
+    let args: Vec<_> = args.iter().skip(1).map(type_name).collect();
+    Error::RuntimeError(format!(
+        "no overload of `{}` accepts the arguments ({}); candidates are:\n    {}",
+        name,
+        args.join(", "),
+        candidates.join("\n    "),
+    ))
+}
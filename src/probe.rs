@@ -0,0 +1,291 @@
+//! Autoref specialization probes, used by the code generated by the
+//! [`metamethods`] attribute to call an operator, or to register a metamethod
+//! for it, only when it is implemented, and to call the index fallbacks
+//! generated by the [`methods`] attribute when there are any.
+//!
+//! [`metamethods`]: ../attr.metamethods.html
+//! [`methods`]: ../attr.methods.html
+use std::marker::PhantomData;
+
+use rlua::{Context, Error, MetaMethod, Result, ToLua, ToLuaMulti, UserData, UserDataMethods, Value};
+
+use crate::ops::{binary, binary_ref, unary};
+
+/// Stands in for the operator `Op` applied to an `L` and an `R`, so that the
+/// generated code can check whether the operator is implemented with autoref
+/// specialization: `(&Probe::<Op, L, R>::new()).apply(ctx, lhs, rhs)` calls the
+/// operator if `L` implements it for `R` and the output can be converted to
+/// lua, and gives `None` otherwise, through [`Fallback`].
+pub struct Probe<Op, L, R>(PhantomData<(Op, L, R)>);
+
+impl<Op, L, R> Probe<Op, L, R> {
+    pub fn new() -> Self {
+        Probe(PhantomData)
+    }
+}
+
+impl<Op, L, R> Default for Probe<Op, L, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Chosen by autoref specialization when `L` doesn't implement the operator
+pub trait Fallback<'lua, L, R> {
+    fn apply(&self, ctx: Context<'lua>, lhs: L, rhs: R) -> Option<Result<Value<'lua>>>;
+}
+
+impl<'lua, Op, L, R> Fallback<'lua, L, R> for &Probe<Op, L, R> {
+    fn apply(&self, _: Context<'lua>, _: L, _: R) -> Option<Result<Value<'lua>>> {
+        None
+    }
+}
+
+macro_rules! binary_probes {
+    ($( $tag:ident, $probe:ident, $op:ident, $method:ident; )*) => {
+        $(
+            #[doc = concat!("Marks a [`Probe`] for `std::ops::", stringify!($op), "`")]
+            pub struct $tag;
+
+            #[doc = concat!("Chosen by autoref specialization when `L` implements `std::ops::", stringify!($op), "<R>`")]
+            pub trait $probe<'lua, L, R> {
+                fn apply(&self, ctx: Context<'lua>, lhs: L, rhs: R) -> Option<Result<Value<'lua>>>;
+            }
+
+            impl<'lua, L, R> $probe<'lua, L, R> for Probe<$tag, L, R>
+            where
+                L: std::ops::$op<R>,
+                L::Output: ToLua<'lua>,
+            {
+                fn apply(&self, ctx: Context<'lua>, lhs: L, rhs: R) -> Option<Result<Value<'lua>>> {
+                    Some(lhs.$method(rhs).to_lua(ctx))
+                }
+            }
+        )*
+    };
+}
+
+binary_probes! {
+    AddOp, AddProbe, Add, add;
+    SubOp, SubProbe, Sub, sub;
+    MulOp, MulProbe, Mul, mul;
+    DivOp, DivProbe, Div, div;
+    RemOp, RemProbe, Rem, rem;
+    BitAndOp, BitAndProbe, BitAnd, bitand;
+    BitOrOp, BitOrProbe, BitOr, bitor;
+    BitXorOp, BitXorProbe, BitXor, bitxor;
+    ShlOp, ShlProbe, Shl, shl;
+    ShrOp, ShrProbe, Shr, shr;
+}
+
+/// Chosen by autoref specialization when the type doesn't implement the
+/// operator, so that no metamethod is registered for it
+pub trait AutoFallback<'lua, T, M> {
+    fn register(&self, methods: &mut M);
+}
+
+impl<'lua, Op, T, M> AutoFallback<'lua, T, M> for &Probe<Op, T, T> {
+    fn register(&self, _: &mut M) {}
+}
+
+macro_rules! auto_binary {
+    ($( $tag:ident, $auto:ident, $op:ident, $method:ident, $meta:ident; )*) => {
+        $(
+            #[doc = concat!("Chosen by autoref specialization when `T` implements `std::ops::", stringify!($op), "`")]
+            pub trait $auto<'lua, T, M> {
+                fn register(&self, methods: &mut M);
+            }
+
+            impl<'lua, T, M> $auto<'lua, T, M> for Probe<$tag, T, T>
+            where
+                T: 'static + UserData + Clone + std::ops::$op,
+                <T as std::ops::$op>::Output: 'static + ToLuaMulti<'lua>,
+                M: UserDataMethods<'lua, T>,
+            {
+                fn register(&self, methods: &mut M) {
+                    binary(methods, MetaMethod::$meta, |lhs: T, rhs: T| std::ops::$op::$method(lhs, rhs));
+                }
+            }
+        )*
+    };
+}
+
+auto_binary! {
+    AddOp, AutoAdd, Add, add, Add;
+    SubOp, AutoSub, Sub, sub, Sub;
+    MulOp, AutoMul, Mul, mul, Mul;
+    DivOp, AutoDiv, Div, div, Div;
+    RemOp, AutoMod, Rem, rem, Mod;
+    BitAndOp, AutoBAnd, BitAnd, bitand, BAnd;
+    BitOrOp, AutoBOr, BitOr, bitor, BOr;
+    BitXorOp, AutoBXor, BitXor, bitxor, BXor;
+    ShlOp, AutoShl, Shl, shl, Shl;
+    ShrOp, AutoShr, Shr, shr, Shr;
+}
+
+macro_rules! auto_unary {
+    ($( $tag:ident, $auto:ident, $op:ident, $method:ident, $meta:ident; )*) => {
+        $(
+            #[doc = concat!("Marks a [`Probe`] for `std::ops::", stringify!($op), "`")]
+            pub struct $tag;
+
+            #[doc = concat!("Chosen by autoref specialization when `T` implements `std::ops::", stringify!($op), "`")]
+            pub trait $auto<'lua, T, M> {
+                fn register(&self, methods: &mut M);
+            }
+
+            impl<'lua, T, M> $auto<'lua, T, M> for Probe<$tag, T, T>
+            where
+                T: 'static + UserData + Clone + std::ops::$op,
+                <T as std::ops::$op>::Output: 'static + ToLuaMulti<'lua>,
+                M: UserDataMethods<'lua, T>,
+            {
+                fn register(&self, methods: &mut M) {
+                    unary(methods, MetaMethod::$meta, |value: T| std::ops::$op::$method(value));
+                }
+            }
+        )*
+    };
+}
+
+auto_unary! {
+    NegOp, AutoUnm, Neg, neg, Unm;
+    NotOp, AutoBNot, Not, not, BNot;
+}
+
+macro_rules! auto_comparison {
+    ($( $tag:ident, $auto:ident, $trait:ident, $op:tt, $meta:ident; )*) => {
+        $(
+            #[doc = concat!("Marks a [`Probe`] for the `", stringify!($op), "` operator")]
+            pub struct $tag;
+
+            #[doc = concat!("Chosen by autoref specialization when `T` implements `std::cmp::", stringify!($trait), "`")]
+            pub trait $auto<'lua, T, M> {
+                fn register(&self, methods: &mut M);
+            }
+
+            impl<'lua, T, M> $auto<'lua, T, M> for Probe<$tag, T, T>
+            where
+                T: 'static + UserData + std::cmp::$trait,
+                M: UserDataMethods<'lua, T>,
+            {
+                fn register(&self, methods: &mut M) {
+                    binary_ref(methods, MetaMethod::$meta, |lhs: &T, rhs: &T| lhs $op rhs);
+                }
+            }
+        )*
+    };
+}
+
+auto_comparison! {
+    EqOp, AutoEq, PartialEq, ==, Eq;
+    LtOp, AutoLt, PartialOrd, <, Lt;
+    LeOp, AutoLe, PartialOrd, <=, Le;
+}
+
+/// Implemented by the [`metamethods`] attribute for a type it generates an
+/// Index metamethod for. Required by [`IndexFallback`], so that a fallback on
+/// a type whose Index isn't generated, and which would never be called, fails
+/// to compile
+///
+/// [`metamethods`]: ../attr.metamethods.html
+pub trait GeneratedIndex {}
+
+/// Implemented by the [`metamethods`] attribute for a type it generates a
+/// NewIndex metamethod for. Required by [`NewIndexFallback`], as with
+/// [`GeneratedIndex`]
+///
+/// [`metamethods`]: ../attr.metamethods.html
+pub trait GeneratedNewIndex {}
+
+/// Implemented by the [`methods`] attribute for a type with a method marked
+/// `#[lua(index_fallback)]`, which the generated Index metamethod calls for
+/// keys that aren't fields
+///
+/// [`methods`]: ../attr.methods.html
+pub trait IndexFallback: GeneratedIndex {
+    fn index_fallback<'lua>(&self, ctx: Context<'lua>, key: Value<'lua>) -> Result<Value<'lua>>;
+}
+
+/// Implemented by the [`methods`] attribute for a type with a method marked
+/// `#[lua(new_index_fallback)]`, which the generated NewIndex metamethod calls
+/// for keys that aren't fields
+///
+/// [`methods`]: ../attr.methods.html
+pub trait NewIndexFallback: GeneratedNewIndex {
+    fn new_index_fallback<'lua>(
+        &mut self,
+        ctx: Context<'lua>,
+        key: Value<'lua>,
+        value: Value<'lua>,
+    ) -> Result<()>;
+}
+
+/// Marks a [`Probe`] for [`IndexFallback`] and [`NewIndexFallback`]
+pub struct FallbackOp;
+
+/// Chosen by autoref specialization when `T` implements [`IndexFallback`]
+pub trait IndexFallbackProbe<'lua, T> {
+    fn index_fallback(&self, data: &T, ctx: Context<'lua>, key: Value<'lua>) -> Option<Result<Value<'lua>>>;
+}
+
+impl<'lua, T: IndexFallback> IndexFallbackProbe<'lua, T> for Probe<FallbackOp, T, T> {
+    fn index_fallback(&self, data: &T, ctx: Context<'lua>, key: Value<'lua>) -> Option<Result<Value<'lua>>> {
+        Some(data.index_fallback(ctx, key))
+    }
+}
+
+/// Chosen by autoref specialization when `T` implements [`NewIndexFallback`]
+pub trait NewIndexFallbackProbe<'lua, T> {
+    fn new_index_fallback(
+        &self,
+        data: &mut T,
+        ctx: Context<'lua>,
+        key: Value<'lua>,
+        value: Value<'lua>,
+    ) -> Option<Result<()>>;
+}
+
+impl<'lua, T: NewIndexFallback> NewIndexFallbackProbe<'lua, T> for Probe<FallbackOp, T, T> {
+    fn new_index_fallback(
+        &self,
+        data: &mut T,
+        ctx: Context<'lua>,
+        key: Value<'lua>,
+        value: Value<'lua>,
+    ) -> Option<Result<()>> {
+        Some(data.new_index_fallback(ctx, key, value))
+    }
+}
+
+/// Chosen by autoref specialization when `T` has no index fallbacks, so that
+/// unknown keys are an error
+pub trait NoFallback<'lua, T> {
+    fn index_fallback(&self, _: &T, _: Context<'lua>, _: Value<'lua>) -> Option<Result<Value<'lua>>> {
+        None
+    }
+
+    fn new_index_fallback(&self, _: &mut T, _: Context<'lua>, _: Value<'lua>, _: Value<'lua>) -> Option<Result<()>> {
+        None
+    }
+}
+
+impl<'lua, T> NoFallback<'lua, T> for &Probe<FallbackOp, T, T> {}
+
+/// The error for a key which is neither a field nor handled by a fallback.
+/// Keys other than strings and numbers are described by their type.
+pub fn no_index(key: &Value) -> Error {
+    let key = match key {
+        Value::String(s) => String::from_utf8_lossy(s.as_bytes()).into_owned(),
+        Value::Integer(i) => i.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Nil => "nil".to_string(),
+        Value::Table(_) => "<table>".to_string(),
+        Value::Function(_) => "<function>".to_string(),
+        Value::Thread(_) => "<thread>".to_string(),
+        Value::UserData(_) | Value::LightUserData(_) => "<userdata>".to_string(),
+        Value::Error(_) => "<error>".to_string(),
+    };
+    Error::RuntimeError(format!("No such index: {}", key))
+}
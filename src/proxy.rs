@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+
+use rlua::{
+    AnyUserData, Context, FromLua, MetaMethod, Result, ToLua, UserData, UserDataMethods, Value,
+};
+
+use crate::forward::{Forward, Forwarder};
+
+/// Map types that can be exposed to lua through a [`MapProxy`]. Implemented
+/// for `HashMap` and `BTreeMap`.
+///
+/// [`MapProxy`]: struct.MapProxy.html
+pub trait ProxyMap: 'static {
+    type Key;
+    type Value;
+
+    /// Copies the value for the given key out of the map, if present
+    fn get(&self, key: &Self::Key) -> Option<Self::Value>;
+    fn insert(&mut self, key: Self::Key, value: Self::Value);
+    fn remove(&mut self, key: &Self::Key);
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copies every entry out of the map, in iteration order
+    fn entries(&self) -> Vec<(Self::Key, Self::Value)>;
+}
+
+impl<K, V, S> ProxyMap for HashMap<K, V, S>
+where
+    K: 'static + Eq + Hash + Clone,
+    V: 'static + Clone,
+    S: 'static + BuildHasher,
+{
+    type Key = K;
+    type Value = V;
+
+    fn get(&self, key: &K) -> Option<V> {
+        HashMap::get(self, key).cloned()
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        HashMap::insert(self, key, value);
+    }
+
+    fn remove(&mut self, key: &K) {
+        HashMap::remove(self, key);
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn entries(&self) -> Vec<(K, V)> {
+        self.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+}
+
+impl<K, V> ProxyMap for BTreeMap<K, V>
+where
+    K: 'static + Ord + Clone,
+    V: 'static + Clone,
+{
+    type Key = K;
+    type Value = V;
+
+    fn get(&self, key: &K) -> Option<V> {
+        BTreeMap::get(self, key).cloned()
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        BTreeMap::insert(self, key, value);
+    }
+
+    fn remove(&mut self, key: &K) {
+        BTreeMap::remove(self, key);
+    }
+
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+
+    fn entries(&self) -> Vec<(K, V)> {
+        self.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+}
+
+/// A user data proxy for a map field of another user data value. Indexing,
+/// assignment, the `#` operator and `pairs` on the proxy all act on the map
+/// inside the parent value, rather than on a copy, so scripts can edit the
+/// map in place. Assigning `nil` to a key removes it. Reading a missing key,
+/// or one that can't be converted to the map's key type, gives `nil`.
+///
+/// The proxy keeps the parent alive for as long as it is reachable from lua.
+/// The [`metamethods`] attribute gives a proxy for a field marked
+/// `#[lua(map_proxy)]` when it is read with Index.
+///
+/// # Examples
+/// ```
+/// # fn test() -> rlua::Result<()> {
+/// use std::collections::HashMap;
+/// use rudeboy::MapProxy;
+///
+/// struct Character {
+///     stats: HashMap<String, i32>,
+/// }
+///
+/// fn stats(character: &mut Character) -> &mut HashMap<String, i32> {
+///     &mut character.stats
+/// }
+///
+/// impl rlua::UserData for Character {
+///     fn add_methods<'lua, M: rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+///         methods.add_function("stats", |ctx, this: rlua::AnyUserData| {
+///             MapProxy::create(ctx, this, stats)
+///         });
+///     }
+/// }
+///
+/// let lua = rlua::Lua::new();
+/// lua.context(|ctx| {
+///     let globals = ctx.globals();
+///     globals.set("hero", Character { stats: HashMap::new() })?;
+///     ctx.load(r#"hero:stats()["hp"] = 10"#).exec()?;
+///
+///     let hero = globals.get::<_, rlua::AnyUserData>("hero")?;
+///     assert_eq!(hero.borrow::<Character>()?.stats["hp"], 10);
+///
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+///
+/// [`metamethods`]: attr.metamethods.html
+pub struct MapProxy<P, M> {
+    field: fn(&mut P) -> &mut M,
+}
+
+impl<P, M> MapProxy<P, M>
+where
+    P: 'static + UserData,
+    M: ProxyMap,
+    M::Key: 'static + Send + for<'lua> FromLua<'lua> + for<'lua> ToLua<'lua>,
+    M::Value: 'static + Send + for<'lua> FromLua<'lua> + for<'lua> ToLua<'lua>,
+{
+    /// Creates a proxy for the map returned by `field` when it is given the
+    /// value inside `parent`. Fails if `parent` is not a user data of type `P`
+    pub fn create<'lua>(
+        ctx: Context<'lua>,
+        parent: AnyUserData<'lua>,
+        field: fn(&mut P) -> &mut M,
+    ) -> Result<AnyUserData<'lua>> {
+        crate::with_ref::<P, _>(&parent, |_| ())?;
+
+        let proxy = ctx.create_userdata(MapProxy { field })?;
+        proxy.set_user_value(parent)?;
+        Ok(proxy)
+    }
+
+    fn with<'lua, R>(proxy: &AnyUserData<'lua>, f: impl FnOnce(&mut M) -> R) -> Result<R> {
+        let field = proxy.borrow::<Self>()?.field;
+        let parent = proxy.get_user_value::<AnyUserData>()?;
+        crate::with_mut(&parent, |parent| f(field(parent)))
+    }
+}
+
+impl<P, M> UserData for MapProxy<P, M>
+where
+    P: 'static + UserData,
+    M: ProxyMap,
+    M::Key: 'static + Send + for<'lua> FromLua<'lua> + for<'lua> ToLua<'lua>,
+    M::Value: 'static + Send + for<'lua> FromLua<'lua> + for<'lua> ToLua<'lua>,
+{
+    fn add_methods<'lua, T: UserDataMethods<'lua, Self>>(methods: &mut T) {
+        // Keys that can't be converted can't be in the map, so reading them
+        // gives nil, as it would for a table
+        methods.add_meta_function(
+            MetaMethod::Index,
+            |ctx, (proxy, key): (AnyUserData, Value)| match <M::Key as FromLua>::from_lua(key, ctx) {
+                Ok(key) => Self::with(&proxy, |map| map.get(&key)),
+                Err(_) => Ok(None),
+            },
+        );
+
+        methods.add_meta_function(
+            MetaMethod::NewIndex,
+            |_, (proxy, key, value): (AnyUserData, M::Key, Option<M::Value>)| {
+                Self::with(&proxy, |map| match value {
+                    Some(value) => map.insert(key, value),
+                    None => map.remove(&key),
+                })
+            },
+        );
+
+        methods.add_meta_function(MetaMethod::Len, |_, proxy: AnyUserData| {
+            Self::with(&proxy, |map| map.len())
+        });
+
+        methods.add_meta_function(MetaMethod::Pairs, |ctx, proxy: AnyUserData| {
+            let entries = Self::with(&proxy, |map| map.entries())?;
+            crate::iter(ctx, entries)
+        });
+    }
+}
+
+/// A user data proxy for a field of another user data value, where the field
+/// is itself user data. The proxy exposes every method and metamethod of the
+/// field's type, but each call borrows back into the parent value, so nested
+/// reads and writes affect the original object rather than a copy.
+///
+/// The field is reached through a pair of accessors: `read` is used, with a
+/// shared borrow of the parent, for methods taking `&self`, and `write`, with
+/// a mutable borrow, for methods taking `&mut self`. The parent is stored as
+/// the proxy's user value, so the proxy keeps it alive for as long as it is
+/// reachable from lua, and it is released along with the proxy. The
+/// [`metamethods`] attribute gives a proxy for a field marked `#[lua(proxy)]`
+/// when it is read with Index.
+///
+/// # Examples
+/// ```
+/// # fn test() -> rlua::Result<()> {
+/// use rudeboy::{methods, user_data, FieldProxy};
+///
+/// #[user_data(Methods)]
+/// struct Bar {
+///     x: i32,
+/// }
+///
+/// #[methods]
+/// impl Bar {
+///     pub fn set_x(&mut self, x: i32) {
+///         self.x = x;
+///     }
+/// }
+///
+/// struct Foo {
+///     bar: Bar,
+/// }
+///
+/// impl rlua::UserData for Foo {
+///     fn add_methods<'lua, M: rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+///         methods.add_function("bar", |ctx, this: rlua::AnyUserData| {
+///             FieldProxy::create(ctx, this, |foo: &Foo| &foo.bar, |foo: &mut Foo| &mut foo.bar)
+///         });
+///     }
+/// }
+///
+/// let lua = rlua::Lua::new();
+/// lua.context(|ctx| {
+///     let globals = ctx.globals();
+///     globals.set("foo", Foo { bar: Bar { x: 1 } })?;
+///     ctx.load("foo:bar():set_x(3)").exec()?;
+///
+///     let foo = globals.get::<_, rlua::AnyUserData>("foo")?;
+///     assert_eq!(foo.borrow::<Foo>()?.bar.x, 3);
+///
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+///
+/// [`metamethods`]: attr.metamethods.html
+pub struct FieldProxy<F> {
+    // The parent's type is erased, so that the proxy's type, and therefore
+    // the methods registered for it, only depend on the field's type
+    read: Box<ReadField<F>>,
+    write: Box<WriteField<F>>,
+}
+
+/// Lends the field inside the given parent user data to a callback
+type ReadField<F> =
+    dyn for<'lua, 'f> Fn(&AnyUserData<'lua>, &mut (dyn FnMut(&F) + 'f)) -> Result<()> + Send;
+type WriteField<F> =
+    dyn for<'lua, 'f> Fn(&AnyUserData<'lua>, &mut (dyn FnMut(&mut F) + 'f)) -> Result<()> + Send;
+
+impl<F: 'static + UserData> FieldProxy<F> {
+    /// Creates a proxy for the field returned by `read` and `write` when they
+    /// are given the value inside `parent`. Fails if `parent` is not a user
+    /// data of type `P`
+    pub fn create<'lua, P: 'static + UserData>(
+        ctx: Context<'lua>,
+        parent: AnyUserData<'lua>,
+        read: fn(&P) -> &F,
+        write: fn(&mut P) -> &mut F,
+    ) -> Result<AnyUserData<'lua>> {
+        crate::with_ref::<P, _>(&parent, |_| ())?;
+
+        let proxy = ctx.create_userdata(FieldProxy {
+            read: Box::new(move |parent, f| crate::with_ref(parent, |parent| f(read(parent)))),
+            write: Box::new(move |parent, f| {
+                crate::with_mut(parent, |parent| f(write(parent)))
+            }),
+        })?;
+        proxy.set_user_value(parent)?;
+        Ok(proxy)
+    }
+}
+
+impl<F: 'static + UserData> Forward<F> for FieldProxy<F> {
+    fn with_ref<'lua, R>(this: &AnyUserData<'lua>, f: impl FnOnce(&F) -> R) -> Result<R> {
+        let parent = this.get_user_value::<AnyUserData>()?;
+        let mut f = Some(f);
+        let mut ret = None;
+        (this.borrow::<Self>()?.read)(&parent, &mut |field| {
+            ret = f.take().map(|f| f(field));
+        })?;
+        Ok(ret.expect("field proxy accessor was not called"))
+    }
+
+    fn with_mut<'lua, R>(this: &AnyUserData<'lua>, f: impl FnOnce(&mut F) -> R) -> Result<R> {
+        let parent = this.get_user_value::<AnyUserData>()?;
+        let mut f = Some(f);
+        let mut ret = None;
+        (this.borrow::<Self>()?.write)(&parent, &mut |field| {
+            ret = f.take().map(|f| f(field));
+        })?;
+        Ok(ret.expect("field proxy accessor was not called"))
+    }
+}
+
+impl<F: 'static + UserData> UserData for FieldProxy<F> {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        F::add_methods(&mut Forwarder::new(methods));
+    }
+}
@@ -0,0 +1,72 @@
+use rlua::{AnyUserData, Context, Result, UserData};
+
+/// Exposes a non-`'static` user data value, such as a view holding
+/// references into rust data, to lua for the duration of the given function.
+/// The value is passed to the function as user data, which can then be handed
+/// to scripts. Once the function returns, the user data is invalidated, and
+/// any further use of it from lua raises an error.
+///
+/// This is a thin wrapper around `rlua::Context::scope` and
+/// `rlua::Scope::create_nonstatic_userdata`. Note that user data created this
+/// way can not be borrowed back as its rust type, and methods registered with
+/// `add_function` rather than `add_method` will not be able to borrow it.
+///
+/// The [`user_data`], [`methods`] and [`metamethods`] attributes can be used
+/// on types with lifetime parameters, in which case the generated code
+/// registers everything with `add_method` so that it works on the borrowed
+/// value. Metamethods taking another operand of the same type, such as Add or
+/// Eq, and chained or consuming methods, can't be generated for such types.
+///
+/// # Examples
+/// ```
+/// # fn test() -> rlua::Result<()> {
+/// use rudeboy::{methods, user_data};
+///
+/// struct World {
+///     names: Vec<String>,
+/// }
+///
+/// #[user_data(Methods)]
+/// struct View<'a> {
+///     world: &'a World,
+/// }
+///
+/// #[methods]
+/// impl<'a> View<'a> {
+///     pub fn count(&self) -> usize {
+///         self.world.names.len()
+///     }
+/// }
+///
+/// let world = World { names: vec!["Eris".to_string(), "Aneris".to_string()] };
+///
+/// let lua = rlua::Lua::new();
+/// lua.context(|ctx| {
+///     let count = ctx.load("function(view) return view:count() end")
+///         .eval::<rlua::Function>()?;
+///
+///     let n = rudeboy::scoped(ctx, View { world: &world }, |_, view| {
+///         count.call::<_, usize>(view)
+///     })?;
+///     assert_eq!(n, 2);
+///
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+///
+/// [`user_data`]: attr.user_data.html
+/// [`methods`]: attr.methods.html
+/// [`metamethods`]: attr.metamethods.html
+pub fn scoped<'lua, T, R, F>(ctx: Context<'lua>, data: T, f: F) -> Result<R>
+where
+    T: UserData,
+    F: FnOnce(Context<'lua>, AnyUserData<'lua>) -> Result<R>,
+{
+    ctx.scope(|scope| {
+        let data = scope.create_nonstatic_userdata(data)?;
+        f(ctx, data)
+    })
+}
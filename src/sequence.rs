@@ -0,0 +1,212 @@
+use rlua::{
+    AnyUserData, Context, Error, FromLua, MetaMethod, Result, ToLua, UserData, UserDataMethods,
+    Value,
+};
+
+/// Returns the integer a lua key holds, if any. Floats with an integral value
+/// are accepted, as they are by lua tables.
+pub fn sequence_key(key: &Value) -> Option<i64> {
+    match *key {
+        Value::Integer(i) => Some(i),
+        Value::Number(n) if n.fract() == 0.0 => Some(n as i64),
+        _ => None,
+    }
+}
+
+fn out_of_bounds(index: i64, len: usize) -> Error {
+    Error::RuntimeError(format!(
+        "index {} is out of bounds for a sequence of length {}",
+        index, len
+    ))
+}
+
+/// Copies the element at the given one-based index out of the sequence. Gives
+/// `None` outside of the sequence, as reading past the end of a lua array gives
+/// `nil`, which is what ends an `ipairs` loop.
+pub fn sequence_get<T: Clone>(seq: &[T], index: i64) -> Option<T> {
+    if index < 1 {
+        return None;
+    }
+    seq.get(index as usize - 1).cloned()
+}
+
+/// Replaces the element at the given one-based index, or appends it if the
+/// index is one past the end. Any other index is an error.
+pub fn sequence_set<T>(seq: &mut Vec<T>, index: i64, value: T) -> Result<()> {
+    let len = seq.len();
+    if index >= 1 && index as usize <= len {
+        seq[index as usize - 1] = value;
+        Ok(())
+    } else if index >= 1 && index as usize == len + 1 {
+        seq.push(value);
+        Ok(())
+    } else {
+        Err(out_of_bounds(index, len))
+    }
+}
+
+/// Inserts an element at the given one-based index, shifting later elements
+/// up. The index may be one past the end, to append.
+fn sequence_insert<T>(seq: &mut Vec<T>, index: i64, value: T) -> Result<()> {
+    let len = seq.len();
+    if index >= 1 && index as usize <= len + 1 {
+        seq.insert(index as usize - 1, value);
+        Ok(())
+    } else {
+        Err(out_of_bounds(index, len))
+    }
+}
+
+/// Registers the `#` operator for a user data type behaving as a lua array
+/// over one of its `Vec` fields.
+pub fn sequence_len<'lua, P, T, M>(methods: &mut M, read: fn(&P) -> &Vec<T>)
+where
+    P: 'static + UserData,
+    T: 'static,
+    M: UserDataMethods<'lua, P>,
+{
+    methods.add_meta_function(MetaMethod::Len, move |_, this: AnyUserData| {
+        crate::with_ref(&this, |data| read(data).len())
+    });
+}
+
+/// Registers the `push`, `pop` and `insert` methods for a user data type
+/// behaving as a lua array over one of its `Vec` fields.
+pub fn sequence_methods<'lua, P, T, M>(methods: &mut M, write: fn(&mut P) -> &mut Vec<T>)
+where
+    P: 'static + UserData,
+    T: 'static + FromLua<'lua> + ToLua<'lua>,
+    M: UserDataMethods<'lua, P>,
+{
+    methods.add_function("push", move |_, (this, value): (AnyUserData, T)| {
+        crate::with_mut(&this, |data| write(data).push(value))
+    });
+
+    methods.add_function("pop", move |_, this: AnyUserData| {
+        crate::with_mut(&this, |data| write(data).pop())
+    });
+
+    methods.add_function("insert", move |_, (this, index, value): (AnyUserData, i64, T)| {
+        crate::with_mut(&this, |data| sequence_insert(write(data), index, value))?
+    });
+}
+
+/// A user data proxy for a `Vec` field of another user data value, which
+/// behaves as a lua array. Indexing with `proxy[i]` reads and writes the
+/// elements in place, with one-based indices, and `#proxy`, `ipairs(proxy)`,
+/// `proxy:push(value)`, `proxy:pop()` and `proxy:insert(i, value)` work as
+/// they would on a lua array.
+///
+/// Reading outside of the sequence gives `nil`, as it would for a table, so
+/// that `ipairs` ends at the last element. Writing is only allowed at an
+/// existing index or one past the end, which appends, and is an error
+/// elsewhere.
+///
+/// The proxy keeps the parent alive for as long as it is reachable from lua.
+/// To make a struct itself behave as an array over one of its fields, mark the
+/// field `#[lua(sequence)]` instead; see the [`metamethods`] attribute.
+///
+/// # Examples
+/// ```
+/// # fn test() -> rlua::Result<()> {
+/// use rudeboy::SequenceProxy;
+///
+/// struct Polygon {
+///     xs: Vec<f64>,
+/// }
+///
+/// fn xs(polygon: &mut Polygon) -> &mut Vec<f64> {
+///     &mut polygon.xs
+/// }
+///
+/// impl rlua::UserData for Polygon {
+///     fn add_methods<'lua, M: rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+///         methods.add_function("xs", |ctx, this: rlua::AnyUserData| {
+///             SequenceProxy::create(ctx, this, xs)
+///         });
+///     }
+/// }
+///
+/// let lua = rlua::Lua::new();
+/// lua.context(|ctx| {
+///     let globals = ctx.globals();
+///     globals.set("poly", Polygon { xs: vec![1.0, 2.0] })?;
+///     ctx.load("local xs = poly:xs(); xs[1] = 5; xs:push(3)").exec()?;
+///
+///     let poly = globals.get::<_, rlua::AnyUserData>("poly")?;
+///     assert_eq!(poly.borrow::<Polygon>()?.xs, vec![5.0, 2.0, 3.0]);
+///
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+///
+/// [`metamethods`]: attr.metamethods.html
+pub struct SequenceProxy<P, T> {
+    field: fn(&mut P) -> &mut Vec<T>,
+}
+
+impl<P, T> SequenceProxy<P, T>
+where
+    P: 'static + UserData,
+    T: 'static + Clone + Send + for<'lua> FromLua<'lua> + for<'lua> ToLua<'lua>,
+{
+    /// Creates a proxy for the `Vec` returned by `field` when it is given the
+    /// value inside `parent`. Fails if `parent` is not a user data of type `P`
+    pub fn create<'lua>(
+        ctx: Context<'lua>,
+        parent: AnyUserData<'lua>,
+        field: fn(&mut P) -> &mut Vec<T>,
+    ) -> Result<AnyUserData<'lua>> {
+        crate::with_ref::<P, _>(&parent, |_| ())?;
+
+        let proxy = ctx.create_userdata(SequenceProxy { field })?;
+        proxy.set_user_value(parent)?;
+        Ok(proxy)
+    }
+
+    fn with<'lua, R>(proxy: &AnyUserData<'lua>, f: impl FnOnce(&mut Vec<T>) -> R) -> Result<R> {
+        let field = proxy.borrow::<Self>()?.field;
+        let parent = proxy.get_user_value::<AnyUserData>()?;
+        crate::with_mut(&parent, |parent| f(field(parent)))
+    }
+}
+
+impl<P, T> UserData for SequenceProxy<P, T>
+where
+    P: 'static + UserData,
+    T: 'static + Clone + Send + for<'lua> FromLua<'lua> + for<'lua> ToLua<'lua>,
+{
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_function(
+            MetaMethod::Index,
+            |_, (proxy, key): (AnyUserData, Value)| match sequence_key(&key) {
+                Some(index) => Self::with(&proxy, |seq| sequence_get(seq, index)),
+                None => Ok(None),
+            },
+        );
+
+        methods.add_meta_function(
+            MetaMethod::NewIndex,
+            |_, (proxy, index, value): (AnyUserData, i64, T)| {
+                Self::with(&proxy, |seq| sequence_set(seq, index, value))?
+            },
+        );
+
+        methods.add_meta_function(MetaMethod::Len, |_, proxy: AnyUserData| {
+            Self::with(&proxy, |seq| seq.len())
+        });
+
+        methods.add_function("push", |_, (proxy, value): (AnyUserData, T)| {
+            Self::with(&proxy, |seq| seq.push(value))
+        });
+
+        methods.add_function("pop", |_, proxy: AnyUserData| Self::with(&proxy, |seq| seq.pop()));
+
+        methods.add_function("insert", |_, (proxy, index, value): (AnyUserData, i64, T)| {
+            Self::with(&proxy, |seq| sequence_insert(seq, index, value))?
+        });
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::{
+    Arc, LockResult, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError, TryLockResult,
+};
+
+use rlua::{AnyUserData, Error, Result, UserData, UserDataMethods};
+
+use crate::forward::{Forward, Forwarder};
+
+/// A shared, thread-safe handle to a user data value. Passing a clone of a
+/// `Shared` to lua lets rust and lua see the same live object: changes made
+/// by scripts are visible from rust, and vice versa, and retrieving the value
+/// from lua doesn't require `T: Clone`.
+///
+/// Every method and metamethod registered by `T`'s `rlua::UserData`
+/// implementation, including those generated by the [`methods`],
+/// [`metamethods`] and [`user_data`] attributes, is forwarded to the inner
+/// value. Methods that are registered as functions rather than methods receive
+/// the `Shared` user data, rather than a `T`, as their arguments.
+///
+/// The value is held in a `RwLock`, so any number of readers may borrow it at
+/// once: methods taking `&self` and operators such as `a + a` or `a == b`
+/// where both operands share the value work as they would on plain user data.
+/// Calling into the value from lua in a way that conflicts with a lock held
+/// from rust, or with a borrow taken re-entrantly by one of its own methods,
+/// raises an error rather than deadlocking.
+///
+/// # Examples
+/// ```
+/// # fn test() -> rlua::Result<()> {
+/// use rudeboy::{methods, user_data, Shared};
+///
+/// #[user_data(Methods)]
+/// struct Counter {
+///     count: u32,
+/// }
+///
+/// #[methods]
+/// impl Counter {
+///     pub fn increment(&mut self) {
+///         self.count += 1;
+///     }
+/// }
+///
+/// let counter = Shared::new(Counter { count: 0 });
+///
+/// let lua = rlua::Lua::new();
+/// lua.context(|ctx| {
+///     ctx.globals().set("counter", counter.clone())?;
+///     ctx.load("counter:increment()").exec()?;
+///
+///     Ok(())
+/// })?;
+///
+/// assert_eq!(counter.read().unwrap().count, 1);
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+///
+/// [`methods`]: attr.methods.html
+/// [`metamethods`]: attr.metamethods.html
+/// [`user_data`]: attr.user_data.html
+pub struct Shared<T>(Arc<RwLock<T>>);
+
+impl<T> Shared<T> {
+    /// Wraps a value to be shared between rust and lua
+    pub fn new(value: T) -> Self {
+        Shared(Arc::new(RwLock::new(value)))
+    }
+
+    /// Locks the inner value for reading, blocking until no writer holds it
+    pub fn read(&self) -> LockResult<RwLockReadGuard<'_, T>> {
+        self.0.read()
+    }
+
+    /// Locks the inner value for writing, blocking until it is available
+    pub fn write(&self) -> LockResult<RwLockWriteGuard<'_, T>> {
+        self.0.write()
+    }
+
+    /// Returns true if both handles share the same value
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+
+    fn try_read(&self) -> Result<RwLockReadGuard<'_, T>> {
+        lock_error(self.0.try_read(), Error::UserDataBorrowError)
+    }
+
+    fn try_write(&self) -> Result<RwLockWriteGuard<'_, T>> {
+        lock_error(self.0.try_write(), Error::UserDataBorrowMutError)
+    }
+}
+
+/// Converts the result of trying to take a lock into the error rlua gives for
+/// the equivalent conflicting borrow of plain user data
+fn lock_error<G>(result: TryLockResult<G>, would_block: Error) -> Result<G> {
+    match result {
+        Ok(guard) => Ok(guard),
+        Err(TryLockError::WouldBlock) => Err(would_block),
+        Err(TryLockError::Poisoned(_)) => Err(Error::RuntimeError(
+            "shared value was poisoned by a panic".to_string(),
+        )),
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Shared(Arc::clone(&self.0))
+    }
+}
+
+impl<T> From<T> for Shared<T> {
+    fn from(value: T) -> Self {
+        Shared::new(value)
+    }
+}
+
+impl<T: 'static + UserData> Forward<T> for Shared<T> {
+    fn with_ref<'lua, R>(this: &AnyUserData<'lua>, f: impl FnOnce(&T) -> R) -> Result<R> {
+        Ok(f(&*this.borrow::<Self>()?.try_read()?))
+    }
+
+    fn with_mut<'lua, R>(this: &AnyUserData<'lua>, f: impl FnOnce(&mut T) -> R) -> Result<R> {
+        Ok(f(&mut *this.borrow::<Self>()?.try_write()?))
+    }
+}
+
+impl<T: 'static + UserData> UserData for Shared<T> {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        T::add_methods(&mut Forwarder::new(methods));
+    }
+}
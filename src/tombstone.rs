@@ -0,0 +1,29 @@
+use rlua::{AnyUserData, Error, Result, UserData, Value};
+
+/// Stored as the user value of a user data whose value is no longer usable
+/// from lua, with the error to raise when it is used
+struct Tombstone(&'static str);
+
+impl UserData for Tombstone {}
+
+/// Marks the given user data as no longer usable, so that borrowing it
+/// through [`with_ref`] or [`with_mut`] raises the given message as an error
+///
+/// [`with_ref`]: ../fn.with_ref.html
+/// [`with_mut`]: ../fn.with_mut.html
+pub(crate) fn bury(value: &AnyUserData, message: &'static str) -> Result<()> {
+    value.set_user_value(Tombstone(message))
+}
+
+/// Fails with the message given to [`bury`] if the given user data was marked
+/// as no longer usable
+///
+/// [`bury`]: fn.bury.html
+pub(crate) fn check(value: &AnyUserData) -> Result<()> {
+    if let Value::UserData(user_value) = value.get_user_value::<Value>()? {
+        if let Ok(tombstone) = user_value.borrow::<Tombstone>() {
+            return Err(Error::RuntimeError(tombstone.0.to_string()));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use std::fmt::Debug;
+
+use rlua::{Context, Error, FromLua, Function, MultiValue, Result, Table, Value};
+
+/// The error raised when a lua value doesn't match any variant of an enum
+/// converted with `#[user_data(as_string)]` or `#[user_data(as_integer)]`,
+/// listing the values that would have
+pub fn unknown_variant<V: Debug, E: Debug>(
+    from: &'static str,
+    to: &'static str,
+    value: V,
+    valid: &[E],
+) -> Error {
+    let valid: Vec<_> = valid.iter().map(|v| format!("{:?}", v)).collect();
+    Error::FromLuaConversionError {
+        from,
+        to,
+        message: Some(format!(
+            "unknown value {:?}, expected one of {}",
+            value,
+            valid.join(", ")
+        )),
+    }
+}
+
+/// The error raised when a constructor table generated for an enum is indexed
+/// with a name that isn't one of its variants
+pub fn no_variant(type_name: &str, key: &str) -> Error {
+    Error::RuntimeError(format!("{} has no variant named '{}'", type_name, key))
+}
+
+/// Checks the number of arguments given to a tuple variant's constructor
+pub fn constructor_args<'lua>(
+    constructor: &str,
+    count: usize,
+    args: MultiValue<'lua>,
+) -> Result<Vec<Value<'lua>>> {
+    if args.len() != count {
+        return Err(Error::RuntimeError(format!(
+            "wrong number of arguments to {} (expected {}, got {})",
+            constructor,
+            count,
+            args.len()
+        )));
+    }
+    Ok(args.into_vec())
+}
+
+/// Reads the fields given to a struct variant's constructor from the table it
+/// is called with, in the order of `names`. Missing fields are `nil`, and any
+/// other key is an error.
+pub fn constructor_fields<'lua>(
+    constructor: &str,
+    names: &[&str],
+    args: MultiValue<'lua>,
+) -> Result<Vec<Value<'lua>>> {
+    let table = match args.into_iter().next() {
+        Some(Value::Table(table)) => table,
+        _ => {
+            return Err(Error::RuntimeError(format!(
+                "bad argument #1 to {} (table of fields expected)",
+                constructor
+            )))
+        }
+    };
+
+    for pair in table.clone().pairs::<Value, Value>() {
+        let (key, _) = pair?;
+        let known = match &key {
+            Value::String(key) => names.iter().any(|name| key.as_bytes() == name.as_bytes()),
+            _ => false,
+        };
+        if !known {
+            let key = match key {
+                Value::String(key) => key.to_str().unwrap_or("?").to_string(),
+                key => format!("{:?}", key),
+            };
+            return Err(Error::RuntimeError(format!(
+                "unknown field '{}' in {}",
+                key, constructor
+            )));
+        }
+    }
+
+    names.iter().map(|name| table.raw_get(*name)).collect()
+}
+
+/// Converts one of the values given to a variant's constructor to the type of
+/// its field, describing the field in the error if it can't be converted
+pub fn constructor_arg<'lua, T: FromLua<'lua>>(
+    ctx: Context<'lua>,
+    constructor: &str,
+    what: &str,
+    value: Value<'lua>,
+) -> Result<T> {
+    T::from_lua(value, ctx).map_err(|e| {
+        Error::RuntimeError(format!("bad {} to {} ({})", what, constructor, e))
+    })
+}
+
+/// Calls the handler for the given variant in a table passed to an enum's
+/// `match` method, with the variant's fields as arguments. Falls back to the
+/// `_` handler, which is called without arguments.
+pub fn match_variant<'lua>(
+    type_name: &str,
+    variant: &str,
+    handlers: Table<'lua>,
+    args: Vec<Value<'lua>>,
+) -> Result<MultiValue<'lua>> {
+    if let Some(handler) = handlers.get::<_, Option<Function>>(variant)? {
+        return handler.call(MultiValue::from_vec(args));
+    }
+    if let Some(handler) = handlers.get::<_, Option<Function>>("_")? {
+        return handler.call(());
+    }
+    Err(Error::RuntimeError(format!(
+        "no handler for {}.{} and no _ fallback in match",
+        type_name, variant
+    )))
+}
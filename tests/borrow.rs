@@ -0,0 +1,174 @@
+use rlua::{AnyUserData, Lua};
+use rudeboy::{
+    metamethods,
+    methods,
+    take,
+    user_data,
+    with_mut,
+    with_ref,
+};
+
+#[test]
+fn borrow_without_clone() -> rlua::Result<()> {
+    #[user_data(Methods)]
+    struct Matrix {
+        pub cells: Vec<f64>,
+    }
+
+    #[methods]
+    impl Matrix {
+        pub fn scale(&mut self, by: f64) {
+            for cell in self.cells.iter_mut() {
+                *cell *= by;
+            }
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("m", Matrix { cells: vec![1.0, 2.0] })?;
+        ctx.load("m:scale(2.0)").exec()?;
+
+        let m = ctx.load("m").eval::<AnyUserData>()?;
+        let sum = with_ref::<Matrix, _>(&m, |m| m.cells.iter().sum::<f64>())?;
+        assert_eq!(sum, 6.0);
+
+        with_mut::<Matrix, _>(&m, |m| m.cells.push(4.0))?;
+        ctx.load("m:scale(0.5)").exec()?;
+        let cells = with_ref::<Matrix, _>(&m, |m| m.cells.clone())?;
+        assert_eq!(cells, vec![1.0, 2.0, 2.0]);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn wrong_type() -> rlua::Result<()> {
+    #[user_data]
+    struct Foo {
+        pub x: i32,
+    }
+
+    #[user_data]
+    struct Bar {
+        pub x: i32,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("foo", Foo { x: 1 })?;
+        globals.set("bar", Bar { x: 2 })?;
+
+        let foo = ctx.load("foo").eval::<AnyUserData>()?;
+        assert!(with_ref::<Bar, _>(&foo, |_| ()).is_err());
+        assert!(with_mut::<Bar, _>(&foo, |_| ()).is_err());
+        assert_eq!(with_ref::<Foo, _>(&foo, |foo| foo.x)?, 1);
+
+        let bar = ctx.load("bar").eval::<AnyUserData>()?;
+        assert!(with_ref::<Foo, _>(&bar, |_| ()).is_err());
+        assert_eq!(with_ref::<Bar, _>(&bar, |bar| bar.x)?, 2);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn already_borrowed() -> rlua::Result<()> {
+    #[user_data]
+    struct Foo {
+        pub x: i32,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("foo", Foo { x: 1 })?;
+
+        let foo = ctx.load("foo").eval::<AnyUserData>()?;
+        with_ref::<Foo, _>(&foo, |outer| {
+            assert_eq!(outer.x, 1);
+            assert!(with_mut::<Foo, _>(&foo, |inner| inner.x = 2).is_err());
+        })?;
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn take_leaves_tombstone() -> rlua::Result<()> {
+    #[metamethods(Index)]
+    #[user_data(Methods, MetaMethods)]
+    #[derive(Default)]
+    struct Matrix {
+        pub cells: Vec<f64>,
+    }
+
+    #[methods]
+    impl Matrix {
+        pub fn len(&self) -> usize {
+            self.cells.len()
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("m", Matrix { cells: vec![1.0, 2.0] })?;
+
+        let m = ctx.load("m").eval::<AnyUserData>()?;
+        let taken = take::<Matrix>(&m)?;
+        assert_eq!(taken.cells, vec![1.0, 2.0]);
+
+        let moved = |res: rlua::Result<()>| match res {
+            Err(rlua::Error::CallbackError { cause, .. }) => {
+                matches!(&*cause, rlua::Error::RuntimeError(msg) if msg == "value moved")
+            }
+            Err(rlua::Error::RuntimeError(msg)) => msg == "value moved",
+            _ => false,
+        };
+        assert!(moved(ctx.load("m:len()").exec()));
+        assert!(moved(ctx.load("local cells = m.cells").exec()));
+        assert!(moved(with_ref::<Matrix, _>(&m, |_| ())));
+        assert!(moved(take::<Matrix>(&m).map(|_| ())));
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn take_wrong_type() -> rlua::Result<()> {
+    #[user_data]
+    #[derive(Default)]
+    struct Foo {
+        pub x: i32,
+    }
+
+    #[user_data]
+    #[derive(Default)]
+    struct Bar {
+        pub x: i32,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("foo", Foo { x: 1 })?;
+
+        let foo = ctx.load("foo").eval::<AnyUserData>()?;
+        assert!(take::<Bar>(&foo).is_err());
+        assert_eq!(with_ref::<Foo, _>(&foo, |foo| foo.x)?, 1);
+        assert_eq!(Bar::default().x, 0);
+
+        with_ref::<Foo, _>(&foo, |_| assert!(take::<Foo>(&foo).is_err()))?;
+        assert_eq!(take::<Foo>(&foo)?.x, 1);
+
+        Ok(())
+    })?;
+    Ok(())
+}
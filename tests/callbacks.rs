@@ -0,0 +1,150 @@
+use rlua::{AnyUserData, Lua};
+use rudeboy::{
+    methods,
+    user_data,
+    LuaCallback,
+};
+
+#[test]
+fn stored_callback() -> rlua::Result<()> {
+    #[user_data(Methods)]
+    struct Timer {
+        pub on_fire: Option<LuaCallback<u32, ()>>,
+    }
+
+    #[methods]
+    impl Timer {
+        pub fn on_fire(&mut self, callback: LuaCallback<u32, ()>) {
+            self.on_fire = Some(callback);
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("timer", Timer { on_fire: None })?;
+        ctx.load("timer:on_fire(function(n) fired = n end)").exec()?;
+
+        let timer = globals.get::<_, AnyUserData>("timer")?;
+        let timer = timer.borrow::<Timer>()?;
+        let callback = timer.on_fire.as_ref().expect("callback was not stored");
+        callback.call(ctx, 23)?;
+        assert_eq!(globals.get::<_, u32>("fired")?, 23);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn typed_return() -> rlua::Result<()> {
+    let lua = Lua::new();
+    let callback = lua.context(|ctx| {
+        let function = ctx.load("function(a, b) return a .. b, #a end").eval()?;
+        LuaCallback::<(String, String), (String, usize)>::new(ctx, function)
+    })?;
+
+    // The callback outlives the context it was created in
+    lua.context(|ctx| {
+        let (joined, len) = callback.call(ctx, ("foo".to_string(), "bar".to_string()))?;
+        assert_eq!(joined, "foobar");
+        assert_eq!(len, 3);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn not_a_function() -> rlua::Result<()> {
+    #[user_data(Methods)]
+    struct Timer {
+        pub on_fire: Option<LuaCallback<(), ()>>,
+    }
+
+    #[methods]
+    impl Timer {
+        pub fn on_fire(&mut self, callback: LuaCallback<(), ()>) {
+            self.on_fire = Some(callback);
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("timer", Timer { on_fire: None })?;
+
+        let res = ctx.load("timer:on_fire(5)").exec();
+        assert!(res.is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn wrong_lua() -> rlua::Result<()> {
+    let lua = Lua::new();
+    let callback = lua.context(|ctx| {
+        let function = ctx.load("function() end").eval()?;
+        LuaCallback::<(), ()>::new(ctx, function)
+    })?;
+
+    let other = Lua::new();
+    other.context(|ctx| {
+        assert!(callback.call(ctx, ()).is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn function_params() -> rlua::Result<()> {
+    #[user_data(Methods)]
+    struct List {
+        pub items: Vec<i32>,
+    }
+
+    #[methods]
+    impl List {
+        #[lua(raise)]
+        pub fn each<'a>(&self, f: rlua::Function<'a>) -> rlua::Result<()> {
+            for item in &self.items {
+                f.call::<_, ()>(*item)?;
+            }
+            Ok(())
+        }
+
+        #[lua(raise)]
+        pub fn map(&mut self, f: rlua::Function) -> rlua::Result<()> {
+            for item in self.items.iter_mut() {
+                *item = f.call(*item)?;
+            }
+            Ok(())
+        }
+
+        pub fn fold(&self, init: i32, f: rlua::Function<'_>) -> rlua::Result<i32> {
+            self.items.iter().try_fold(init, |acc, item| f.call((acc, *item)))
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("list", List { items: vec![1, 2, 3] })?;
+
+        ctx.load("seen = {}; list:each(function(x) seen[#seen + 1] = x end)").exec()?;
+        assert_eq!(ctx.load("table.concat(seen, ',')").eval::<String>()?, "1,2,3");
+
+        ctx.load("list:map(function(x) return x * 10 end)").exec()?;
+        assert_eq!(ctx.load("list:fold(0, function(a, x) return a + x end)").eval::<i32>()?, 60);
+
+        let res = ctx.load("list:each(function(x) error('stop') end)").exec();
+        assert!(res.is_err());
+        assert!(ctx.load("list:each(5)").exec().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use rlua::Lua;
+use rudeboy::{methods, user_data, RudeboyTable};
+
+#[user_data(Methods, Constructors)]
+#[derive(Clone, PartialEq, Debug)]
+enum Foo {
+    Bar,
+    Baz(u32),
+    Qux { x: i32, y: i32 },
+    Named(String, Option<u8>),
+}
+
+#[methods]
+impl Foo {
+    pub fn double(&mut self) {
+        match self {
+            Foo::Bar | Foo::Named(..) => (),
+            Foo::Baz(x) => *x *= 2,
+            Foo::Qux { x, y } => {
+                *x *= 2;
+                *y *= 2;
+            }
+        }
+    }
+}
+
+fn error_message(err: rlua::Error) -> String {
+    match err {
+        rlua::Error::CallbackError { cause, .. } => error_message((*cause).clone()),
+        rlua::Error::RuntimeError(msg) => msg,
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn construct() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("Foo", Foo::generate_table(ctx)?)?;
+
+        assert_eq!(ctx.load("Foo.Bar").eval::<Foo>()?, Foo::Bar);
+        assert_eq!(ctx.load("Foo.Baz(5)").eval::<Foo>()?, Foo::Baz(5));
+        assert_eq!(ctx.load("Foo.Qux{ x = 2, y = 3 }").eval::<Foo>()?, Foo::Qux { x: 2, y: 3 });
+        assert_eq!(
+            ctx.load("Foo.Named('a', nil)").eval::<Foo>()?,
+            Foo::Named("a".to_string(), None),
+        );
+        assert_eq!(
+            ctx.load("Foo.Named('a', 1)").eval::<Foo>()?,
+            Foo::Named("a".to_string(), Some(1)),
+        );
+
+        let doubled = ctx.load("local qux = Foo.Qux{ x = 2, y = 3 }; qux:double(); return qux").eval::<Foo>()?;
+        assert_eq!(doubled, Foo::Qux { x: 4, y: 6 });
+
+        // Unit variants aren't shared between lookups
+        ctx.load("local bar = Foo.Bar; bar:double()").exec()?;
+        assert!(ctx.load("Foo.Bar ~= Foo.Bar").eval::<bool>()?);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn construct_errors() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("Foo", Foo::generate_table(ctx)?)?;
+
+        let error = |code: &str| error_message(ctx.load(code).exec().unwrap_err());
+
+        assert_eq!(error("local _ = Foo.Quux"), "Foo has no variant named 'Quux'");
+        assert_eq!(
+            error("Foo.Baz()"),
+            "wrong number of arguments to Foo.Baz (expected 1, got 0)",
+        );
+        assert!(error("Foo.Baz('five')").starts_with("bad argument #1 to Foo.Baz ("));
+        assert!(error("Foo.Qux{ x = 2 }").starts_with("bad field 'y' to Foo.Qux ("));
+        assert_eq!(
+            error("Foo.Qux{ x = 2, y = 3, z = 4 }"),
+            "unknown field 'z' in Foo.Qux",
+        );
+        assert_eq!(
+            error("Foo.Qux(2, 3)"),
+            "bad argument #1 to Foo.Qux (table of fields expected)",
+        );
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn construct_generic() -> rlua::Result<()> {
+    #[user_data(Constructors)]
+    #[derive(Clone, PartialEq, Debug)]
+    enum Slot<T>
+    where
+        T: 'static + Clone + Send + for<'lua> rlua::FromLua<'lua> + for<'lua> rlua::ToLua<'lua>,
+    {
+        Empty,
+        Full(T),
+        Pair { first: T, second: T },
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("IntSlot", Slot::<i64>::generate_table(ctx)?)?;
+        globals.set("StringSlot", Slot::<String>::generate_table(ctx)?)?;
+
+        assert_eq!(ctx.load("IntSlot.Empty").eval::<Slot<i64>>()?, Slot::Empty);
+        assert_eq!(ctx.load("IntSlot.Full(3)").eval::<Slot<i64>>()?, Slot::Full(3));
+        assert_eq!(
+            ctx.load("StringSlot.Pair{ first = 'a', second = 'b' }").eval::<Slot<String>>()?,
+            Slot::Pair { first: "a".to_string(), second: "b".to_string() },
+        );
+        assert!(ctx.load("IntSlot.Full('three')").exec().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
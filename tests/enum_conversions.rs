@@ -0,0 +1,125 @@
+use rlua::Lua;
+use rudeboy::{methods, user_data, RudeboyTable};
+
+#[test]
+fn as_string() -> rlua::Result<()> {
+    #[user_data(as_string)]
+    #[derive(PartialEq, Debug)]
+    enum Sign {
+        Minus,
+        Plus,
+    }
+
+    #[user_data(Methods)]
+    struct Number {
+        pub value: i32,
+    }
+
+    #[methods]
+    impl Number {
+        pub fn signed(&self, sign: Sign) -> i32 {
+            match sign {
+                Sign::Minus => -self.value,
+                Sign::Plus => self.value,
+            }
+        }
+
+        pub fn sign(&self) -> Sign {
+            if self.value < 0 {
+                Sign::Minus
+            } else {
+                Sign::Plus
+            }
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("five", Number { value: 5 })?;
+        globals.set("minus", Sign::Minus)?;
+
+        assert_eq!(ctx.load("minus").eval::<String>()?, "Minus");
+        assert_eq!(ctx.load("five:sign()").eval::<String>()?, "Plus");
+        assert_eq!(ctx.load("five:signed('Minus')").eval::<i32>()?, -5);
+        assert_eq!(ctx.load("'Plus'").eval::<Sign>()?, Sign::Plus);
+
+        match ctx.load("'minus'").eval::<Sign>() {
+            Err(rlua::Error::FromLuaConversionError { message: Some(message), .. }) => {
+                assert_eq!(message, r#"unknown value "minus", expected one of "Minus", "Plus""#);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn as_string_rename_all() -> rlua::Result<()> {
+    #[user_data(as_string, rename_all = "snake_case")]
+    #[derive(PartialEq, Debug)]
+    enum Weather {
+        Sunny,
+        PartlyCloudy,
+        HTTPError,
+    }
+
+    #[user_data(as_string, rename_all = "SCREAMING-KEBAB-CASE")]
+    #[derive(PartialEq, Debug)]
+    enum Mode {
+        ReadWrite,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("weather", Weather::PartlyCloudy)?;
+        globals.set("mode", Mode::ReadWrite)?;
+
+        assert_eq!(ctx.load("weather").eval::<String>()?, "partly_cloudy");
+        assert_eq!(ctx.load("mode").eval::<String>()?, "READ-WRITE");
+        assert_eq!(ctx.load("'sunny'").eval::<Weather>()?, Weather::Sunny);
+        assert_eq!(ctx.load("'http_error'").eval::<Weather>()?, Weather::HTTPError);
+        assert!(ctx.load("'Sunny'").eval::<Weather>().is_err());
+        assert!(ctx.load("1").eval::<Weather>().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn as_integer() -> rlua::Result<()> {
+    #[user_data(as_integer)]
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    enum Color {
+        Red = 1,
+        Green,
+        Blue = 5,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("Color", Color::generate_table(ctx)?)?;
+        globals.set("green", Color::Green)?;
+
+        assert!(ctx.load("Color.Red == 1").eval::<bool>()?);
+        assert!(ctx.load("green == Color.Green and green == 2").eval::<bool>()?);
+        assert_eq!(ctx.load("Color.Blue").eval::<Color>()?, Color::Blue);
+        assert_eq!(ctx.load("5").eval::<Color>()?, Color::Blue);
+
+        match ctx.load("3").eval::<Color>() {
+            Err(rlua::Error::FromLuaConversionError { message: Some(message), .. }) => {
+                assert_eq!(message, "unknown value 3, expected one of 1, 2, 5");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(ctx.load("'red'").eval::<Color>().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
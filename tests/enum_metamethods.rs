@@ -609,3 +609,44 @@ fn bnot() -> rlua::Result<()> {
     })?;
     Ok(())
 }
+
+#[test]
+fn pairs() -> rlua::Result<()> {
+    #[metamethods(Pairs)]
+    #[user_data(MetaMethods)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect {
+            width: f64,
+            #[lua(rename = "h")]
+            height: f64,
+            #[lua(skip)]
+            _cache: Option<f64>,
+        },
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("empty", Shape::Empty)?;
+        globals.set("circle", Shape::Circle(2.5))?;
+        globals.set("rect", Shape::Rect { width: 2.0, height: 3.0, _cache: None })?;
+
+        ctx.load(r#"
+            function dump(value)
+                local out = ""
+                for key, value in pairs(value) do
+                    out = out .. key .. "=" .. value .. ";"
+                end
+                return out
+            end
+        "#).exec()?;
+        assert_eq!(ctx.load("dump(empty)").eval::<String>()?, "");
+        assert_eq!(ctx.load("dump(circle)").eval::<String>()?, "1=2.5;");
+        assert_eq!(ctx.load("dump(rect)").eval::<String>()?, "width=2.0;h=3.0;");
+
+        Ok(())
+    })?;
+    Ok(())
+}
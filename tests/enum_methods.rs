@@ -235,3 +235,144 @@ fn multi_mix() -> rlua::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn match_variant() -> rlua::Result<()> {
+    #[user_data(Match)]
+    #[derive(Clone)]
+    enum Foo {
+        Bar,
+        Baz(u32),
+        Qux { x: i32, y: i32 },
+        Quux(String, #[lua(skip)] Vec<u8>),
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("bar", Foo::Bar)?;
+        globals.set("baz", Foo::Baz(5))?;
+        globals.set("qux", Foo::Qux { x: 2, y: 3 })?;
+        globals.set("quux", Foo::Quux("quux".to_string(), vec![1]))?;
+
+        ctx.load(r#"
+            function describe(foo)
+                return foo:match{
+                    Bar = function() return "bar" end,
+                    Baz = function(n) return "baz " .. n end,
+                    Qux = function(x, y) return "qux " .. x .. " " .. y end,
+                    _ = function() return "other" end,
+                }
+            end
+        "#).exec()?;
+
+        assert_eq!(ctx.load("describe(bar)").eval::<String>()?, "bar");
+        assert_eq!(ctx.load("describe(baz)").eval::<String>()?, "baz 5");
+        assert_eq!(ctx.load("describe(qux)").eval::<String>()?, "qux 2 3");
+        assert_eq!(ctx.load("describe(quux)").eval::<String>()?, "other");
+        assert_eq!(
+            ctx.load("quux:match{ Quux = function(s, rest) return s, rest end }").eval::<(String, Option<u8>)>()?,
+            ("quux".to_string(), None),
+        );
+        let quux = globals.get::<_, rlua::AnyUserData>("quux")?;
+        if let Foo::Quux(_, bytes) = &*quux.borrow::<Foo>()? {
+            assert_eq!(bytes, &vec![1]);
+        }
+
+        match ctx.load("baz:match{ Bar = function() end }").exec() {
+            Err(rlua::Error::CallbackError { cause, .. }) => match &*cause {
+                rlua::Error::RuntimeError(msg) => {
+                    assert_eq!(msg, "no handler for Foo.Baz and no _ fallback in match")
+                }
+                other => panic!("unexpected error: {:?}", other),
+            },
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn variant_predicates() -> rlua::Result<()> {
+    use rudeboy::metamethods;
+
+    #[metamethods(Add)]
+    #[user_data(MetaMethods)]
+    #[derive(Clone, Copy)]
+    enum Bit {
+        Zero,
+        One,
+        Overflow,
+        CarryOut(u8),
+    }
+
+    impl std::ops::Add for Bit {
+        type Output = Self;
+
+        fn add(self, other: Self) -> Self {
+            match (self, other) {
+                (Bit::Zero, rhs) => rhs,
+                (lhs, Bit::Zero) => lhs,
+                (Bit::One, Bit::One) => Bit::Overflow,
+                _ => Bit::CarryOut(1),
+            }
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("zero", Bit::Zero)?;
+        globals.set("one", Bit::One)?;
+
+        assert!(ctx.load("(zero + zero):is_zero()").eval::<bool>()?);
+        assert!(ctx.load("(one + one):is_overflow()").eval::<bool>()?);
+        assert!(!ctx.load("one:is_overflow()").eval::<bool>()?);
+        assert!(ctx.load("one:is_one()").eval::<bool>()?);
+        assert!(ctx.load("(one + one + one):is_carry_out()").eval::<bool>()?);
+
+        assert_eq!(ctx.load("one:variant_name()").eval::<String>()?, "One");
+        assert_eq!(ctx.load("(one + one):variant_name()").eval::<String>()?, "Overflow");
+        assert_eq!(ctx.load("one:variant_index()").eval::<usize>()?, 1);
+        assert_eq!(ctx.load("(one + one + one):variant_index()").eval::<usize>()?, 3);
+        assert!(matches!(ctx.load("one + one + one").eval::<Bit>()?, Bit::CarryOut(1)));
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn match_is_opt_in() -> rlua::Result<()> {
+    // Not ToLua, which only Match requires of the fields
+    struct Handle(u32);
+
+    #[user_data]
+    enum Resource {
+        Free,
+        Held(Handle),
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("free", Resource::Free)?;
+        globals.set("held", Resource::Held(Handle(3)))?;
+
+        assert!(ctx.load("free:is_free()").eval::<bool>()?);
+        assert!(ctx.load("held:is_held()").eval::<bool>()?);
+        assert_eq!(ctx.load("held:variant_name()").eval::<String>()?, "Held");
+        assert!(ctx.load("held:match{ _ = function() end }").exec().is_err());
+
+        let held = globals.get::<_, rlua::AnyUserData>("held")?;
+        assert!(matches!(*held.borrow::<Resource>()?, Resource::Held(Handle(3))));
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
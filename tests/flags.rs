@@ -0,0 +1,69 @@
+#![cfg(feature = "bitflags")]
+
+use rlua::Lua;
+use rudeboy::{bitflags, user_data, RudeboyTable};
+
+#[user_data(flags)]
+bitflags! {
+    struct Perms: u32 {
+        const READ = 0b001;
+        const WRITE = 0b010;
+        const EXECUTE = 0b100;
+        const READ_WRITE = Self::READ.bits | Self::WRITE.bits;
+    }
+}
+
+#[test]
+fn operators() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("Perms", Perms::generate_table(ctx)?)?;
+
+        assert_eq!(ctx.load("Perms.READ | Perms.WRITE").eval::<Perms>()?, Perms::READ_WRITE);
+        assert_eq!(ctx.load("Perms.READ_WRITE & Perms.WRITE").eval::<Perms>()?, Perms::WRITE);
+        assert_eq!(ctx.load("Perms.READ_WRITE ~ Perms.READ").eval::<Perms>()?, Perms::WRITE);
+        assert_eq!(
+            ctx.load("~Perms.READ").eval::<Perms>()?,
+            Perms::WRITE | Perms::EXECUTE,
+        );
+        assert!(ctx.load("Perms.READ | Perms.WRITE == Perms.READ_WRITE").eval::<bool>()?);
+        assert!(ctx.load("local _ = Perms.DELETE").exec().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn methods() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("Perms", Perms::generate_table(ctx)?)?;
+        globals.set("perms", Perms::READ)?;
+
+        assert!(ctx.load("perms:contains(Perms.READ)").eval::<bool>()?);
+        assert!(!ctx.load("perms:contains(Perms.READ_WRITE)").eval::<bool>()?);
+
+        ctx.load("perms:insert(Perms.EXECUTE)").exec()?;
+        assert_eq!(globals.get::<_, Perms>("perms")?, Perms::READ | Perms::EXECUTE);
+        assert_eq!(ctx.load("tostring(perms)").eval::<String>()?, "READ | EXECUTE");
+
+        ctx.load("perms:remove(Perms.READ_WRITE)").exec()?;
+        assert_eq!(globals.get::<_, Perms>("perms")?, Perms::EXECUTE);
+
+        // Looking up a flag gives a new value each time
+        assert_eq!(ctx.load("Perms.READ").eval::<Perms>()?, Perms::READ);
+
+        ctx.load("perms:remove(Perms.EXECUTE)").exec()?;
+        assert_eq!(ctx.load("tostring(perms)").eval::<String>()?, "(empty)");
+        assert_eq!(
+            ctx.load("tostring(Perms.READ_WRITE)").eval::<String>()?,
+            "READ | WRITE | READ_WRITE",
+        );
+
+        Ok(())
+    })?;
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use rlua::{AnyUserData, Lua};
+use rudeboy::{
+    methods,
+    user_data,
+    Handle,
+};
+
+#[user_data(Methods)]
+struct Entity {
+    pub hp: i32,
+}
+
+#[methods]
+impl Entity {
+    pub fn hp(&self) -> i32 {
+        self.hp
+    }
+
+    pub fn damage(&mut self, amount: i32) {
+        self.hp -= amount;
+    }
+}
+
+#[test]
+fn across_contexts() -> rlua::Result<()> {
+    let lua = Lua::new();
+    let player = lua.context(|ctx| {
+        ctx.globals().set("player", Entity { hp: 10 })?;
+        let player = ctx.load("player").eval::<AnyUserData>()?;
+        Handle::<Entity>::new(ctx, player)
+    })?;
+
+    lua.context(|ctx| {
+        ctx.load("player:damage(3)").exec()?;
+        assert_eq!(player.borrow(ctx, |player| player.hp)?, 7);
+
+        Ok(())
+    })?;
+
+    lua.context(|ctx| {
+        player.borrow_mut(ctx, |player| player.hp = 20)?;
+        assert_eq!(ctx.load("player:hp()").eval::<i32>()?, 20);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn keeps_value_alive() -> rlua::Result<()> {
+    let lua = Lua::new();
+    let entity = lua.context(|ctx| {
+        let entity = ctx.create_userdata(Entity { hp: 5 })?;
+        Handle::<Entity>::new(ctx, entity)
+    })?;
+
+    lua.context(|ctx| {
+        ctx.load("collectgarbage()").exec()?;
+        assert_eq!(entity.borrow(ctx, |entity| entity.hp)?, 5);
+
+        ctx.globals().set("entity", entity.user_data(ctx)?)?;
+        assert_eq!(ctx.load("entity:hp()").eval::<i32>()?, 5);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn wrong_type() -> rlua::Result<()> {
+    #[user_data]
+    struct Item {
+        pub weight: f64,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let item = ctx.create_userdata(Item { weight: 1.5 })?;
+        assert!(Handle::<Entity>::new(ctx, item.clone()).is_err());
+
+        let item = Handle::<Item>::new(ctx, item)?;
+        assert_eq!(item.borrow(ctx, |item| item.weight)?, 1.5);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn wrong_lua() -> rlua::Result<()> {
+    let lua = Lua::new();
+    let entity = lua.context(|ctx| {
+        let entity = ctx.create_userdata(Entity { hp: 5 })?;
+        Handle::<Entity>::new(ctx, entity)
+    })?;
+
+    let other = Lua::new();
+    other.context(|ctx| {
+        match entity.borrow(ctx, |entity| entity.hp) {
+            Err(rlua::Error::MismatchedRegistryKey) => {}
+            res => panic!("expected MismatchedRegistryKey, got {:?}", res),
+        }
+
+        Ok(())
+    })?;
+    Ok(())
+}
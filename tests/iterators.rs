@@ -0,0 +1,174 @@
+use rlua::{Lua, UserData, UserDataMethods};
+use rudeboy::{methods, user_data};
+
+#[test]
+fn single_values() -> rlua::Result<()> {
+    struct Polygon {
+        pub xs: Vec<f64>,
+    }
+
+    impl UserData for Polygon {
+        fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+            methods.add_method("xs", |ctx, data, ()| {
+                rudeboy::iter(ctx, data.xs.clone())
+            });
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("poly", Polygon { xs: vec![1.0, 2.0, 3.5] })?;
+
+        let sum = ctx.load(r#"
+            local sum = 0
+            for x in poly:xs() do
+                sum = sum + x
+            end
+            return sum
+        "#).eval::<f64>()?;
+        assert_eq!(sum, 6.5);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn tuples() -> rlua::Result<()> {
+    struct Inventory {
+        pub items: Vec<(String, u32)>,
+    }
+
+    impl UserData for Inventory {
+        fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+            methods.add_method("items", |ctx, data, ()| {
+                rudeboy::iter(ctx, data.items.clone())
+            });
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        let items = vec![("apple".to_string(), 2), ("pear".to_string(), 3)];
+        globals.set("inventory", Inventory { items })?;
+
+        let out = ctx.load(r#"
+            local out = ""
+            for name, count in inventory:items() do
+                out = out .. name .. count
+            end
+            return out
+        "#).eval::<String>()?;
+        assert_eq!(out, "apple2pear3");
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn lazy() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        // An unbounded iterator only works if items are produced on demand
+        globals.set("naturals", rudeboy::iter(ctx, 1..)?)?;
+
+        let sum = ctx.load(r#"
+            local sum = 0
+            for n in naturals do
+                if n > 4 then break end
+                sum = sum + n
+            end
+            return sum
+        "#).eval::<u64>()?;
+        assert_eq!(sum, 10);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn exhausted() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("next_item", rudeboy::iter(ctx, vec![1])?)?;
+
+        assert_eq!(ctx.load("next_item()").eval::<Option<i32>>()?, Some(1));
+        assert_eq!(ctx.load("next_item()").eval::<Option<i32>>()?, None);
+        assert_eq!(ctx.load("next_item()").eval::<Option<i32>>()?, None);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn methods() -> rlua::Result<()> {
+    #[user_data(Methods)]
+    struct Inventory {
+        pub items: Vec<(String, u32)>,
+    }
+
+    #[methods]
+    impl Inventory {
+        #[lua(iter)]
+        pub fn names(&self) -> Vec<String> {
+            self.items.iter().map(|(name, _)| name.clone()).collect()
+        }
+
+        #[lua(cursor)]
+        pub fn items(&self, index: usize) -> Option<(&str, u32)> {
+            self.items.get(index).map(|(name, count)| (name.as_str(), *count))
+        }
+
+        #[lua(iter)]
+        pub fn up_to(&self, count: u32) -> rlua::Result<std::ops::RangeInclusive<u32>> {
+            Ok(1..=count)
+        }
+
+        #[lua(iter)]
+        pub fn counts(&self) -> Vec<Option<u32>> {
+            vec![Some(1), None, Some(3)]
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        let items = vec![("apple".to_string(), 2), ("pear".to_string(), 3)];
+        globals.set("inventory", Inventory { items })?;
+
+        let out = ctx.load(r#"
+            local out = ""
+            for name in inventory:names() do
+                out = out .. name
+            end
+            for name, count in inventory:items() do
+                out = out .. name .. count
+            end
+            for n in inventory:up_to(3) do
+                out = out .. n
+            end
+            return out
+        "#).eval::<String>()?;
+        assert_eq!(out, "applepearapple2pear3123");
+
+        // The loop ends at the first item that converts to nil
+        let sum = ctx.load(r#"
+            local sum = 0
+            for n in inventory:counts() do
+                sum = sum + n
+            end
+            return sum
+        "#).eval::<u32>()?;
+        assert_eq!(sum, 1);
+
+        Ok(())
+    })?;
+    Ok(())
+}
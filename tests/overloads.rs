@@ -0,0 +1,145 @@
+use rlua::Lua;
+use rudeboy::{methods, user_data};
+
+#[user_data]
+#[derive(Clone, Debug, PartialEq)]
+struct Vec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[user_data(Methods)]
+struct Sprite {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[methods]
+impl Sprite {
+    pub fn position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    #[lua(overload = "move")]
+    pub fn move_xy(&mut self, x: f64, y: f64) {
+        self.x = x;
+        self.y = y;
+    }
+
+    #[lua(overload = "move")]
+    pub fn move_vec(&mut self, to: Vec2) {
+        self.x = to.x;
+        self.y = to.y;
+    }
+
+    #[lua(overload = "move")]
+    pub fn move_named(&mut self, name: String) -> bool {
+        match name.as_str() {
+            "origin" => {
+                self.x = 0.0;
+                self.y = 0.0;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn sprite() -> Sprite {
+    Sprite { x: 1.0, y: 2.0 }
+}
+
+#[test]
+fn by_arity() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("sprite", sprite())?;
+        globals.set("target", Vec2 { x: 5.0, y: 6.0 })?;
+
+        ctx.load("sprite:move(3, 4)").exec()?;
+        assert_eq!(ctx.load("sprite:position()").eval::<(f64, f64)>()?, (3.0, 4.0));
+
+        ctx.load("sprite:move(target)").exec()?;
+        assert_eq!(ctx.load("sprite:position()").eval::<(f64, f64)>()?, (5.0, 6.0));
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn by_type() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("sprite", sprite())?;
+
+        // The first overload taking one argument expects a Vec2, so a string
+        // falls through to the next one
+        assert!(ctx.load(r#"sprite:move("origin")"#).eval::<bool>()?);
+        assert_eq!(ctx.load("sprite:position()").eval::<(f64, f64)>()?, (0.0, 0.0));
+        assert!(!ctx.load(r#"sprite:move("nowhere")"#).eval::<bool>()?);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn no_match() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("sprite", sprite())?;
+
+        let err = match ctx.load("sprite:move(1, 2, 3)").exec() {
+            Err(rlua::Error::CallbackError { cause, .. }) => cause.to_string(),
+            res => panic!("expected a callback error, got {:?}", res),
+        };
+        assert!(err.contains("no overload of `move` accepts the arguments (number, number, number)"));
+        assert!(err.contains("move(x: f64, y: f64)"));
+        assert!(err.contains("move(to: Vec2)"));
+        assert!(err.contains("move(name: String)"));
+
+        assert!(ctx.load("sprite:move({})").exec().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn skipped() -> rlua::Result<()> {
+    #[user_data(Methods)]
+    struct Foo {
+        pub bar: u8,
+    }
+
+    #[methods]
+    impl Foo {
+        pub fn get(&self) -> u8 {
+            self.bar
+        }
+
+        #[lua(skip)]
+        pub fn get_ref(&self) -> &u8 {
+            &self.bar
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("foo", Foo { bar: 23 })?;
+
+        assert_eq!(ctx.load("foo:get()").eval::<u8>()?, 23);
+        assert!(ctx.load("foo:get_ref()").exec().is_err());
+
+        let foo = globals.get::<_, rlua::AnyUserData>("foo")?;
+        assert_eq!(*foo.borrow::<Foo>()?.get_ref(), 23);
+
+        Ok(())
+    })?;
+    Ok(())
+}
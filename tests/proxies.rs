@@ -0,0 +1,400 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rlua::{AnyUserData, Lua, ToLua, UserData, UserDataMethods};
+use rudeboy::{
+    metamethods,
+    methods,
+    user_data,
+    FieldProxy,
+    MapProxy,
+    RudeboyMetaMethods,
+    RudeboyMethods,
+    Shared,
+};
+
+struct Character {
+    pub stats: HashMap<String, i32>,
+    pub log: BTreeMap<u32, String>,
+}
+
+fn stats(character: &mut Character) -> &mut HashMap<String, i32> {
+    &mut character.stats
+}
+
+fn log(character: &mut Character) -> &mut BTreeMap<u32, String> {
+    &mut character.log
+}
+
+impl UserData for Character {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_function("stats", |ctx, this: AnyUserData| {
+            MapProxy::create(ctx, this, stats)
+        });
+        methods.add_function("log", |ctx, this: AnyUserData| {
+            MapProxy::create(ctx, this, log)
+        });
+    }
+}
+
+fn hero() -> Character {
+    let mut stats = HashMap::new();
+    stats.insert("hp".to_string(), 5);
+    stats.insert("mp".to_string(), 3);
+    Character { stats, log: BTreeMap::new() }
+}
+
+#[test]
+fn map_index() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("hero", hero())?;
+
+        assert_eq!(ctx.load(r#"hero:stats()["hp"]"#).eval::<i32>()?, 5);
+        assert_eq!(ctx.load("hero:stats().mp").eval::<i32>()?, 3);
+        assert_eq!(ctx.load("hero:stats().xp").eval::<Option<i32>>()?, None);
+        assert_eq!(ctx.load("#hero:stats()").eval::<usize>()?, 2);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn map_assign_in_place() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("hero", hero())?;
+
+        ctx.load(r#"
+            local stats = hero:stats()
+            stats.hp = 10
+            stats.xp = 1
+            stats.mp = nil
+        "#).exec()?;
+
+        let hero = globals.get::<_, AnyUserData>("hero")?;
+        let hero = hero.borrow::<Character>()?;
+        assert_eq!(hero.stats.get("hp"), Some(&10));
+        assert_eq!(hero.stats.get("xp"), Some(&1));
+        assert_eq!(hero.stats.get("mp"), None);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn map_pairs() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("hero", hero())?;
+
+        ctx.load(r#"
+            local log = hero:log()
+            log[2] = "b"
+            log[1] = "a"
+            log[3] = "c"
+        "#).exec()?;
+
+        let out = ctx.load(r#"
+            local out = ""
+            for k, v in pairs(hero:log()) do
+                out = out .. k .. v
+            end
+            return out
+        "#).eval::<String>()?;
+        assert_eq!(out, "1a2b3c");
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn map_bad_key() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("hero", hero())?;
+
+        // Reading a key of the wrong type gives nil, like a missing key...
+        assert_eq!(ctx.load(r#"hero:log()["one"]"#).eval::<Option<String>>()?, None);
+        assert_eq!(ctx.load("hero:log().foo").eval::<Option<String>>()?, None);
+        assert_eq!(ctx.load("hero:log()[7]").eval::<Option<String>>()?, None);
+
+        // ...but it can't be assigned to
+        let bad_key = ctx.load(r#"hero:log()["one"] = "a""#).exec();
+        assert!(bad_key.is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn map_proxy_field() -> rlua::Result<()> {
+    #[metamethods(Index)]
+    #[user_data(MetaMethods)]
+    struct Npc {
+        pub name: String,
+        #[lua(map_proxy)]
+        pub stats: HashMap<String, i32>,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        let npc = Npc { name: "Eris".to_string(), stats: hero().stats };
+        globals.set("npc", npc)?;
+        let shared = Npc { name: "Kallisti".to_string(), stats: HashMap::new() };
+        let shared = Shared::new(shared);
+        globals.set("shared", shared.clone())?;
+
+        ctx.load(r#"
+            npc.stats.hp = 10
+            npc.stats["mp"] = nil
+            shared.stats.xp = 7
+        "#).exec()?;
+        assert_eq!(ctx.load("npc.stats.hp").eval::<i32>()?, 10);
+        assert_eq!(ctx.load("#npc.stats").eval::<usize>()?, 1);
+        assert_eq!(ctx.load("npc.name").eval::<String>()?, "Eris");
+
+        let npc = globals.get::<_, AnyUserData>("npc")?;
+        let npc = npc.borrow::<Npc>()?;
+        assert_eq!(npc.stats.get("hp"), Some(&10));
+        assert_eq!(npc.stats.get("mp"), None);
+        assert_eq!(shared.read().unwrap().stats.get("xp"), Some(&7));
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn proxy_field() -> rlua::Result<()> {
+    #[metamethods(Index, NewIndex)]
+    #[user_data(MetaMethods)]
+    #[derive(Clone)]
+    struct Inner {
+        pub x: i32,
+    }
+
+    #[metamethods(Index, NewIndex)]
+    #[user_data(MetaMethods)]
+    struct Outer {
+        #[lua(proxy)]
+        pub inner: Inner,
+        #[lua(proxy)]
+        pub nested: Outer2,
+    }
+
+    #[metamethods(Index)]
+    #[user_data(MetaMethods)]
+    #[derive(Clone)]
+    struct Outer2 {
+        #[lua(proxy)]
+        pub inner: Inner,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("outer", Outer {
+            inner: Inner { x: 1 },
+            nested: Outer2 { inner: Inner { x: 2 } },
+        })?;
+
+        ctx.load("outer.inner.x = 3").exec()?;
+        assert_eq!(ctx.load("outer.inner.x").eval::<i32>()?, 3);
+
+        // Proxies of proxies still reach the original object
+        ctx.load("local inner = outer.nested.inner; inner.x = inner.x + 5").exec()?;
+
+        // Assigning to the field itself replaces the value
+        ctx.load("outer.inner = outer.nested.inner").exec()?;
+
+        let outer = globals.get::<_, AnyUserData>("outer")?;
+        let outer = outer.borrow::<Outer>()?;
+        assert_eq!(outer.nested.inner.x, 7);
+        assert_eq!(outer.inner.x, 7);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[metamethods(Index)]
+struct Bar {
+    pub number: f64,
+}
+
+#[methods]
+impl Bar {
+    pub fn set_number(&mut self, number: f64) {
+        self.number = number;
+    }
+}
+
+impl UserData for Bar {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        Bar::generate_metamethods(methods);
+        Bar::generate_methods(methods);
+    }
+}
+
+struct Foo {
+    pub number: f64,
+    pub bar: Bar,
+}
+
+fn bar(parent: &Foo) -> &Bar {
+    &parent.bar
+}
+
+fn bar_mut(parent: &mut Foo) -> &mut Bar {
+    &mut parent.bar
+}
+
+impl UserData for Foo {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_function(
+            rlua::MetaMethod::Index,
+            |ctx, (this, key): (AnyUserData, String)| {
+                match key.as_str() {
+                    "bar" => FieldProxy::create(ctx, this, bar, bar_mut)?.to_lua(ctx),
+                    "number" => this.borrow::<Foo>()?.number.to_lua(ctx),
+                    _ => Err(rlua::Error::RuntimeError(format!("no field {}", key))),
+                }
+            },
+        );
+    }
+}
+
+#[test]
+fn nested_proxy() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("foo", Foo { number: 23.0, bar: Bar { number: 5.0 } })?;
+
+        assert_eq!(ctx.load("foo.number").eval::<f64>()?, 23.0);
+        assert_eq!(ctx.load("foo.bar.number").eval::<f64>()?, 5.0);
+
+        ctx.load("foo.bar:set_number(3.0)").exec()?;
+        assert_eq!(ctx.load("foo.bar.number").eval::<f64>()?, 3.0);
+
+        let foo = globals.get::<_, AnyUserData>("foo")?;
+        assert_eq!(foo.borrow::<Foo>()?.bar.number, 3.0);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn nested_proxy_outlives_lookup() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("foo", Foo { number: 23.0, bar: Bar { number: 5.0 } })?;
+
+        ctx.load(r#"
+            local bar = foo.bar
+            foo = nil
+            collectgarbage()
+            bar:set_number(7.0)
+            number = bar.number
+        "#).exec()?;
+        assert_eq!(globals.get::<_, f64>("number")?, 7.0);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn nested_proxy_read_while_parent_borrowed() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("foo", Foo { number: 23.0, bar: Bar { number: 5.0 } })?;
+        ctx.load("bar = foo.bar").exec()?;
+
+        let foo = globals.get::<_, AnyUserData>("foo")?;
+        let _foo = foo.borrow::<Foo>()?;
+
+        // Reads only need a shared borrow of the parent...
+        assert_eq!(ctx.load("bar.number").eval::<f64>()?, 5.0);
+
+        // ...but writes need it exclusively
+        assert!(ctx.load("bar:set_number(1.0)").exec().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn nested_proxy_operators() -> rlua::Result<()> {
+    #[metamethods(Add, Eq, Lt)]
+    #[user_data(MetaMethods)]
+    #[derive(Clone, Debug, PartialEq, PartialOrd)]
+    struct Point {
+        pub x: i32,
+    }
+
+    impl std::ops::Add for Point {
+        type Output = Point;
+        fn add(self, other: Point) -> Point {
+            Point { x: self.x + other.x }
+        }
+    }
+
+    struct Line {
+        pub start: Point,
+        pub end: Point,
+    }
+
+    impl UserData for Line {
+        fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+            methods.add_function("start", |ctx, this: AnyUserData| {
+                FieldProxy::create(ctx, this, |line: &Line| &line.start, |line: &mut Line| &mut line.start)
+            });
+            methods.add_function("finish", |ctx, this: AnyUserData| {
+                FieldProxy::create(ctx, this, |line: &Line| &line.end, |line: &mut Line| &mut line.end)
+            });
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("line", Line { start: Point { x: 1 }, end: Point { x: 4 } })?;
+        globals.set("one", Point { x: 1 })?;
+        ctx.load("s = line:start() f = line:finish()").exec()?;
+
+        assert_eq!(ctx.load("s + f").eval::<Point>()?, Point { x: 5 });
+        assert_eq!(ctx.load("f + f").eval::<Point>()?, Point { x: 8 });
+        assert_eq!(ctx.load("f + one").eval::<Point>()?, Point { x: 5 });
+        assert!(ctx.load("s == one").eval::<bool>()?);
+        assert!(ctx.load("s == line:start()").eval::<bool>()?);
+        assert!(ctx.load("s < f").eval::<bool>()?);
+
+        // Both operands borrow the shared parent at once
+        let shared = Shared::new(Line { start: Point { x: 2 }, end: Point { x: 3 } });
+        globals.set("shared", shared.clone())?;
+        ctx.load("ss = shared:start() sf = shared:finish()").exec()?;
+
+        assert_eq!(ctx.load("ss + sf").eval::<Point>()?, Point { x: 5 });
+        assert_eq!(ctx.load("sf + sf").eval::<Point>()?, Point { x: 6 });
+        assert!(ctx.load("ss < sf").eval::<bool>()?);
+        assert!(ctx.load("ss == shared:start()").eval::<bool>()?);
+        assert_eq!(shared.read().unwrap().start, Point { x: 2 });
+
+        Ok(())
+    })?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use rlua::{Function, Lua, UserData, UserDataMethods};
+
+struct World {
+    pub names: Vec<String>,
+}
+
+struct View<'a> {
+    pub world: &'a mut World,
+}
+
+impl<'a> UserData for View<'a> {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("count", |_, view, ()| Ok(view.world.names.len()));
+        methods.add_method_mut("add", |_, view, name: String| {
+            view.world.names.push(name);
+            Ok(())
+        });
+    }
+}
+
+#[test]
+fn borrowed_data() -> rlua::Result<()> {
+    let mut world = World { names: vec!["Eris".to_string()] };
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let script = ctx.load(r#"
+            function(view)
+                view:add("Aneris")
+                return view:count()
+            end
+        "#).eval::<Function>()?;
+
+        let count = rudeboy::scoped(ctx, View { world: &mut world }, |_, view| {
+            script.call::<_, usize>(view)
+        })?;
+        assert_eq!(count, 2);
+
+        Ok(())
+    })?;
+
+    assert_eq!(world.names, vec!["Eris".to_string(), "Aneris".to_string()]);
+    Ok(())
+}
+
+#[test]
+fn invalidated_after_scope() -> rlua::Result<()> {
+    let world = World { names: Vec::new() };
+
+    struct ReadView<'a> {
+        pub world: &'a World,
+    }
+
+    impl<'a> UserData for ReadView<'a> {
+        fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+            methods.add_method("count", |_, view, ()| Ok(view.world.names.len()));
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        rudeboy::scoped(ctx, ReadView { world: &world }, |_, view| {
+            globals.set("view", view)
+        })?;
+
+        assert!(ctx.load("view:count()").exec().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn generated_methods() -> rlua::Result<()> {
+    use rudeboy::{metamethods, methods, user_data};
+
+    #[metamethods(Index, NewIndex, Pairs)]
+    #[user_data(Methods, MetaMethods)]
+    struct Cursor<'a> {
+        #[lua(skip)]
+        pub names: &'a [String],
+        pub pos: usize,
+    }
+
+    #[methods]
+    impl<'a> Cursor<'a> {
+        pub fn current(&self) -> Option<String> {
+            self.names.get(self.pos).cloned()
+        }
+
+        pub fn advance(&mut self, by: usize) -> bool {
+            self.pos += by;
+            self.pos < self.names.len()
+        }
+
+        #[meta(ToString)]
+        pub fn describe(&self) -> String {
+            format!("cursor at {}", self.pos)
+        }
+
+        #[lua(index_fallback)]
+        pub fn position(&self, name: String) -> Option<usize> {
+            self.names.iter().position(|n| *n == name)
+        }
+    }
+
+    let names = vec!["Eris".to_string(), "Aneris".to_string(), "Discord".to_string()];
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let script = ctx.load(r#"
+            function(cursor)
+                local seen = {}
+                repeat
+                    seen[#seen + 1] = cursor:current()
+                until not cursor:advance(1)
+                cursor.pos = 1
+                local keys = 0
+                for _ in pairs(cursor) do
+                    keys = keys + 1
+                end
+                return table.concat(seen, ","), cursor:current(), cursor.pos, keys, tostring(cursor), cursor.Discord
+            end
+        "#).eval::<Function>()?;
+
+        let cursor = Cursor { names: &names, pos: 0 };
+        let (seen, current, pos, keys, described, discord) = rudeboy::scoped(ctx, cursor, |_, cursor| {
+            script.call::<_, (String, String, usize, usize, String, usize)>(cursor)
+        })?;
+        assert_eq!(seen, "Eris,Aneris,Discord");
+        assert_eq!(current, "Aneris");
+        assert_eq!(pos, 1);
+        assert_eq!(keys, 1);
+        assert_eq!(described, "cursor at 1");
+        assert_eq!(discord, 2);
+
+        Ok(())
+    })?;
+    Ok(())
+}
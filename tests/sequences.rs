@@ -0,0 +1,174 @@
+use rlua::{AnyUserData, Lua};
+use rudeboy::{metamethods, user_data, SequenceProxy};
+
+#[metamethods(Index)]
+#[user_data(MetaMethods)]
+struct Polygon {
+    pub name: String,
+    #[lua(sequence)]
+    pub xs: Vec<f64>,
+}
+
+fn polygon() -> Polygon {
+    Polygon {
+        name: "triangle".to_string(),
+        xs: vec![1.0, 2.0, 3.0],
+    }
+}
+
+#[test]
+fn index() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("poly", polygon())?;
+
+        assert_eq!(ctx.load("poly[1]").eval::<f64>()?, 1.0);
+        assert_eq!(ctx.load("poly[3]").eval::<f64>()?, 3.0);
+        assert_eq!(ctx.load("poly[0]").eval::<Option<f64>>()?, None);
+        assert_eq!(ctx.load("poly[4]").eval::<Option<f64>>()?, None);
+        assert_eq!(ctx.load("#poly").eval::<usize>()?, 3);
+
+        // Fields are still available by name
+        assert_eq!(ctx.load("poly.name").eval::<String>()?, "triangle");
+        assert!(ctx.load("poly.bad_index").exec().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn new_index() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("poly", polygon())?;
+
+        ctx.load("poly[2] = 5; poly[4] = 6").exec()?;
+        let poly = globals.get::<_, AnyUserData>("poly")?;
+        assert_eq!(poly.borrow::<Polygon>()?.xs, vec![1.0, 5.0, 3.0, 6.0]);
+
+        assert!(ctx.load("poly[0] = 1").exec().is_err());
+        assert!(ctx.load("poly[6] = 1").exec().is_err());
+        assert!(ctx.load("poly[1] = 'one'").exec().is_err());
+        assert!(ctx.load("poly.name = 'square'").exec().is_err());
+        assert_eq!(poly.borrow::<Polygon>()?.xs.len(), 4);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn ipairs() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("poly", polygon())?;
+
+        let out = ctx.load(r#"
+            local out = ""
+            for i, x in ipairs(poly) do
+                out = out .. i .. "=" .. x .. ";"
+            end
+            return out
+        "#).eval::<String>()?;
+        assert_eq!(out, "1=1.0;2=2.0;3=3.0;");
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn push_pop_insert() -> rlua::Result<()> {
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("poly", polygon())?;
+
+        ctx.load("poly:push(4); poly:insert(1, 0); poly:insert(6, 5)").exec()?;
+        let poly = globals.get::<_, AnyUserData>("poly")?;
+        assert_eq!(poly.borrow::<Polygon>()?.xs, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        assert_eq!(ctx.load("poly:pop()").eval::<f64>()?, 5.0);
+        assert!(ctx.load("poly:insert(0, 1)").exec().is_err());
+        assert!(ctx.load("poly:insert(7, 1)").exec().is_err());
+        assert_eq!(poly.borrow::<Polygon>()?.xs.len(), 5);
+
+        ctx.load("while #poly > 0 do poly:pop() end").exec()?;
+        assert_eq!(ctx.load("poly:pop()").eval::<Option<f64>>()?, None);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn without_index() -> rlua::Result<()> {
+    #[metamethods]
+    #[user_data(MetaMethods)]
+    struct Inventory(#[lua(sequence)] Vec<String>);
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("inventory", Inventory(vec!["apple".to_string()]))?;
+
+        ctx.load(r#"inventory:push("pear")"#).exec()?;
+        assert_eq!(ctx.load("inventory[2]").eval::<String>()?, "pear");
+        assert_eq!(ctx.load("#inventory").eval::<usize>()?, 2);
+        assert!(ctx.load("inventory.name").exec().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn proxy() -> rlua::Result<()> {
+    struct Path {
+        pub points: Vec<i32>,
+    }
+
+    fn points(path: &mut Path) -> &mut Vec<i32> {
+        &mut path.points
+    }
+
+    impl rlua::UserData for Path {
+        fn add_methods<'lua, M: rlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+            methods.add_function("points", |ctx, this: AnyUserData| {
+                SequenceProxy::create(ctx, this, points)
+            });
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("path", Path { points: vec![1, 2] })?;
+
+        let sum = ctx.load(r#"
+            local points = path:points()
+            points[1] = 10
+            points:push(3)
+            points:insert(1, 0)
+            local sum = 0
+            for _, p in ipairs(points) do
+                sum = sum + p
+            end
+            return sum
+        "#).eval::<i32>()?;
+        assert_eq!(sum, 15);
+        assert_eq!(ctx.load("#path:points()").eval::<usize>()?, 4);
+        assert_eq!(ctx.load("path:points():pop()").eval::<i32>()?, 3);
+        assert!(ctx.load("path:points()[5] = 1").exec().is_err());
+
+        let path = globals.get::<_, AnyUserData>("path")?;
+        assert_eq!(path.borrow::<Path>()?.points, vec![0, 10, 2]);
+
+        Ok(())
+    })?;
+    Ok(())
+}
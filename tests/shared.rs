@@ -0,0 +1,180 @@
+use rlua::Lua;
+use rudeboy::{
+    metamethods,
+    methods,
+    user_data,
+    Shared,
+};
+
+#[test]
+fn methods() -> rlua::Result<()> {
+    #[user_data(Methods)]
+    struct Counter {
+        pub count: u32,
+    }
+
+    #[methods]
+    impl Counter {
+        pub fn get(&self) -> u32 {
+            self.count
+        }
+
+        pub fn add(&mut self, amount: u32) {
+            self.count += amount;
+        }
+    }
+
+    let counter = Shared::new(Counter { count: 1 });
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("counter", counter.clone())?;
+
+        assert_eq!(ctx.load("counter:get()").eval::<u32>()?, 1);
+        ctx.load("counter:add(4)").exec()?;
+        assert_eq!(ctx.load("counter:get()").eval::<u32>()?, 5);
+
+        // Changes made from rust are visible in lua
+        counter.write().unwrap().count = 10;
+        assert_eq!(ctx.load("counter:get()").eval::<u32>()?, 10);
+
+        Ok(())
+    })?;
+
+    // Changes made from lua are visible in rust
+    assert_eq!(counter.read().unwrap().count, 10);
+    Ok(())
+}
+
+#[test]
+fn metamethods() -> rlua::Result<()> {
+    #[metamethods(Index)]
+    #[user_data(MetaMethods)]
+    struct Person {
+        pub name: String,
+    }
+
+    let person = Shared::new(Person { name: "Eris".to_string() });
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("eris", person.clone())?;
+
+        assert_eq!(ctx.load("eris.name").eval::<String>()?, "Eris");
+        person.write().unwrap().name = "Discordia".to_string();
+        assert_eq!(ctx.load("eris.name").eval::<String>()?, "Discordia");
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn retrieve_without_clone() -> rlua::Result<()> {
+    #[user_data(Methods)]
+    struct Counter {
+        pub count: u32,
+    }
+
+    #[methods]
+    impl Counter {
+        pub fn add(&mut self, amount: u32) {
+            self.count += amount;
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("counter", Shared::new(Counter { count: 0 }))?;
+        ctx.load("counter:add(3)").exec()?;
+
+        let counter = ctx.load("counter").eval::<Shared<Counter>>()?;
+        assert_eq!(counter.read().unwrap().count, 3);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn locked_from_rust() -> rlua::Result<()> {
+    #[user_data(Methods)]
+    struct Counter {
+        pub count: u32,
+    }
+
+    #[methods]
+    impl Counter {
+        pub fn get(&self) -> u32 {
+            self.count
+        }
+    }
+
+    let counter = Shared::new(Counter { count: 0 });
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("counter", counter.clone())?;
+
+        {
+            let _guard = counter.read().unwrap();
+            assert_eq!(ctx.load("counter:get()").eval::<u32>()?, 0);
+        }
+
+        let _guard = counter.write().unwrap();
+        assert!(ctx.load("counter:get()").eval::<u32>().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn operators() -> rlua::Result<()> {
+    #[metamethods(Add, Eq, Lt)]
+    #[user_data(MetaMethods)]
+    #[derive(Clone, Debug, PartialEq, PartialOrd)]
+    struct Money {
+        pub cents: u64,
+    }
+
+    impl std::ops::Add for Money {
+        type Output = Money;
+        fn add(self, other: Money) -> Money {
+            Money { cents: self.cents + other.cents }
+        }
+    }
+
+    let wallet = Shared::new(Money { cents: 150 });
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("a", wallet.clone())?;
+        globals.set("b", Shared::new(Money { cents: 100 }))?;
+        globals.set("c", Money { cents: 100 })?;
+        globals.set("same", wallet.clone())?;
+
+        // Two shared values
+        assert_eq!(ctx.load("a + b").eval::<Money>()?, Money { cents: 250 });
+        assert!(!ctx.load("a == b").eval::<bool>()?);
+        assert!(ctx.load("b < a").eval::<bool>()?);
+        assert!(!ctx.load("a < b").eval::<bool>()?);
+
+        // A shared value and a plain one
+        assert_eq!(ctx.load("c + a").eval::<Money>()?, Money { cents: 250 });
+        assert!(ctx.load("b == c").eval::<bool>()?);
+        assert!(ctx.load("c < a").eval::<bool>()?);
+
+        // Two user data holding the same shared value
+        assert!(ctx.load("a == same").eval::<bool>()?);
+        assert_eq!(ctx.load("a + same").eval::<Money>()?, Money { cents: 300 });
+
+        Ok(())
+    })?;
+    Ok(())
+}
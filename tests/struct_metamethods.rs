@@ -73,6 +73,141 @@ fn nested_index() -> rlua::Result<()> {
     Ok(())
 }
 
+#[test]
+fn index_skip_rename() -> rlua::Result<()> {
+    #[metamethods(Index)]
+    #[user_data(MetaMethods)]
+    struct Account {
+        #[lua(rename = "name")]
+        pub owner: String,
+        #[lua(skip)]
+        pub password: String,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        let owner = "Eris".to_string();
+        let password = "hunter2".to_string();
+        globals.set("account", Account { owner, password })?;
+
+        assert_eq!(ctx.load("account.name").eval::<String>()?, "Eris");
+        assert!(ctx.load("account.owner").eval::<String>().is_err());
+        assert!(ctx.load("account.password").eval::<String>().is_err());
+
+        let account = globals.get::<_, rlua::AnyUserData>("account")?;
+        assert_eq!(account.borrow::<Account>()?.password, "hunter2");
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn new_index() -> rlua::Result<()> {
+    #[metamethods(Index, NewIndex)]
+    #[user_data(MetaMethods)]
+    struct Person {
+        pub name: String,
+        #[lua(rename = "age")]
+        pub years: u32,
+        #[lua(skip)]
+        pub id: u32,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("eris", Person { name: "Eris".to_string(), years: 23, id: 5 })?;
+
+        ctx.load(r#"eris.name = "Discordia"; eris.age = eris.age + 1"#).exec()?;
+        assert_eq!(ctx.load("eris.name").eval::<String>()?, "Discordia");
+        assert_eq!(ctx.load("eris.age").eval::<u32>()?, 24);
+
+        assert!(ctx.load("eris.age = 'old'").exec().is_err());
+        assert!(ctx.load("eris.years = 1").exec().is_err());
+        assert!(ctx.load("eris.id = 1").exec().is_err());
+
+        let eris = globals.get::<_, rlua::AnyUserData>("eris")?;
+        let eris = eris.borrow::<Person>()?;
+        assert_eq!((eris.years, eris.id), (24, 5));
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn pairs() -> rlua::Result<()> {
+    #[metamethods(Pairs)]
+    #[user_data(MetaMethods)]
+    struct Person {
+        pub name: String,
+        #[lua(rename = "age")]
+        pub years: u32,
+        #[lua(skip)]
+        pub secret: String,
+        pub nickname: Option<String>,
+        pub number: f64,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("eris", Person {
+            name: "Eris".to_string(),
+            years: 23,
+            secret: "apple".to_string(),
+            nickname: None,
+            number: 5.0,
+        })?;
+
+        // Fields are visited in declaration order, including nil ones
+        let out = ctx.load(r#"
+            local out = ""
+            for key, value in pairs(eris) do
+                out = out .. key .. "=" .. tostring(value) .. ";"
+            end
+            return out
+        "#).eval::<String>()?;
+        assert_eq!(out, "name=Eris;age=23;nickname=nil;number=5.0;");
+
+        let eris = globals.get::<_, rlua::AnyUserData>("eris")?;
+        assert_eq!(eris.borrow::<Person>()?.secret, "apple");
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn pairs_tuple() -> rlua::Result<()> {
+    #[metamethods(Pairs)]
+    #[user_data(MetaMethods)]
+    struct Point(i32, #[lua(skip)] i32, i32);
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("point", Point(1, 2, 3))?;
+
+        let out = ctx.load(r#"
+            local out = ""
+            for key, value in pairs(point) do
+                out = out .. key .. "=" .. value .. ";"
+            end
+            return out
+        "#).eval::<String>()?;
+        assert_eq!(out, "1=1;3=3;");
+
+        let point = globals.get::<_, rlua::AnyUserData>("point")?;
+        assert_eq!(point.borrow::<Point>()?.1, 2);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
 #[test]
 fn add() -> rlua::Result<()> {
     #[metamethods(Add)]
@@ -556,3 +691,307 @@ fn bnot() -> rlua::Result<()> {
     })?;
     Ok(())
 }
+
+#[test]
+fn mixed_operands() -> rlua::Result<()> {
+    #[metamethods(Mul(f64), Add(Self, i32))]
+    #[user_data(MetaMethods)]
+    #[derive(Clone, Debug, Copy)]
+    struct Vec2 {
+        pub x: f64,
+        pub y: f64,
+    }
+
+    impl std::ops::Mul<f64> for Vec2 {
+        type Output = Self;
+
+        fn mul(self, by: f64) -> Self {
+            Vec2 { x: self.x * by, y: self.y * by }
+        }
+    }
+
+    impl std::ops::Mul<Vec2> for f64 {
+        type Output = Vec2;
+
+        fn mul(self, v: Vec2) -> Vec2 {
+            v * self
+        }
+    }
+
+    impl std::ops::Add for Vec2 {
+        type Output = Self;
+
+        fn add(self, other: Self) -> Self {
+            Vec2 { x: self.x + other.x, y: self.y + other.y }
+        }
+    }
+
+    impl std::ops::Add<i32> for Vec2 {
+        type Output = Self;
+
+        fn add(self, by: i32) -> Self {
+            Vec2 { x: self.x + by as f64, y: self.y + by as f64 }
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("v", Vec2 { x: 1.0, y: 2.0 })?;
+
+        let res = ctx.load("v * 3").eval::<Vec2>()?;
+        assert_eq!((res.x, res.y), (3.0, 6.0));
+        let res = ctx.load("0.5 * v").eval::<Vec2>()?;
+        assert_eq!((res.x, res.y), (0.5, 1.0));
+
+        let res = ctx.load("v + v").eval::<Vec2>()?;
+        assert_eq!((res.x, res.y), (2.0, 4.0));
+        let res = ctx.load("v + 2").eval::<Vec2>()?;
+        assert_eq!((res.x, res.y), (3.0, 4.0));
+
+        // Only Vec2 + i32 is implemented, not i32 + Vec2
+        assert!(ctx.load("2 + v").eval::<Vec2>().is_err());
+        assert!(ctx.load("v * v").eval::<Vec2>().is_err());
+        assert!(ctx.load("v * 'x'").eval::<Vec2>().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn add_by_ref() -> rlua::Result<()> {
+    // Not Clone, so it can only be added through references
+    #[metamethods(Add(by_ref), Mul(by_ref))]
+    #[user_data(MetaMethods)]
+    struct Big {
+        pub limbs: Vec<u64>,
+    }
+
+    impl std::ops::Add for &Big {
+        type Output = Big;
+
+        fn add(self, other: Self) -> Big {
+            let limbs = self.limbs.iter().zip(&other.limbs).map(|(a, b)| a + b).collect();
+            Big { limbs }
+        }
+    }
+
+    impl std::ops::Mul for &Big {
+        type Output = Big;
+
+        fn mul(self, other: Self) -> Big {
+            let limbs = self.limbs.iter().zip(&other.limbs).map(|(a, b)| a * b).collect();
+            Big { limbs }
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("a", Big { limbs: vec![1, 2] })?;
+        globals.set("b", Big { limbs: vec![3, 4] })?;
+
+        let res = ctx.load("(a + b) * a").eval::<rlua::AnyUserData>()?;
+        let limbs = rudeboy::with_ref::<Big, _>(&res, |big| big.limbs.clone())?;
+        assert_eq!(limbs, vec![4, 12]);
+
+        let res = ctx.load("a + a").eval::<rlua::AnyUserData>()?;
+        let limbs = rudeboy::with_ref::<Big, _>(&res, |big| big.limbs.clone())?;
+        assert_eq!(limbs, vec![2, 4]);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn auto() -> rlua::Result<()> {
+    #[metamethods(auto, Index, Mul(f64))]
+    #[user_data(MetaMethods)]
+    #[derive(Clone, Debug, Copy, PartialEq, PartialOrd)]
+    struct Foo {
+        pub bar: f64,
+    }
+
+    impl std::ops::Add for Foo {
+        type Output = Self;
+
+        fn add(self, other: Self) -> Self {
+            Foo { bar: self.bar + other.bar }
+        }
+    }
+
+    impl std::ops::Neg for Foo {
+        type Output = Self;
+
+        fn neg(self) -> Self {
+            Foo { bar: -self.bar }
+        }
+    }
+
+    impl std::ops::Mul<f64> for Foo {
+        type Output = Self;
+
+        fn mul(self, by: f64) -> Self {
+            Foo { bar: self.bar * by }
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("one", Foo { bar: 1.0 })?;
+        globals.set("two", Foo { bar: 2.0 })?;
+
+        assert_eq!(ctx.load("(one + two).bar").eval::<f64>()?, 3.0);
+        assert_eq!(ctx.load("(-two).bar").eval::<f64>()?, -2.0);
+        assert_eq!(ctx.load("(two * 4).bar").eval::<f64>()?, 8.0);
+        assert!(ctx.load("one == one").eval::<bool>()?);
+        assert!(ctx.load("one < two and one <= one").eval::<bool>()?);
+
+        // No Sub or Div impl, so no metamethod
+        assert!(ctx.load("one - two").exec().is_err());
+        assert!(ctx.load("one / two").exec().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn index_fallback() -> rlua::Result<()> {
+    use std::collections::HashMap;
+    use rudeboy::methods;
+
+    #[metamethods(Index, NewIndex)]
+    #[user_data(MetaMethods, Methods)]
+    struct Entity {
+        pub name: String,
+        #[lua(skip)]
+        pub attributes: HashMap<String, f64>,
+    }
+
+    #[methods]
+    impl Entity {
+        pub fn count(&self) -> usize {
+            self.attributes.len()
+        }
+
+        #[lua(index_fallback)]
+        pub fn attribute(&self, key: String) -> Option<f64> {
+            self.attributes.get(&key).copied()
+        }
+
+        #[lua(new_index_fallback)]
+        pub fn set_attribute(&mut self, key: String, value: f64) -> rlua::Result<()> {
+            if key.starts_with('_') {
+                return Err(rlua::Error::RuntimeError(format!("{} is private", key)));
+            }
+            self.attributes.insert(key, value);
+            Ok(())
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        let attributes = vec![("hp".to_string(), 10.0)].into_iter().collect();
+        globals.set("entity", Entity { name: "Eris".to_string(), attributes })?;
+
+        assert_eq!(ctx.load("entity.name").eval::<String>()?, "Eris");
+        assert_eq!(ctx.load("entity.hp").eval::<f64>()?, 10.0);
+        assert_eq!(ctx.load("entity.mp").eval::<Option<f64>>()?, None);
+
+        ctx.load("entity.mp = 3; entity.name = 'Aneris'").exec()?;
+        assert_eq!(ctx.load("entity.mp").eval::<f64>()?, 3.0);
+        assert_eq!(ctx.load("entity.name").eval::<String>()?, "Aneris");
+        assert_eq!(ctx.load("entity:count()").eval::<usize>()?, 2);
+        assert!(ctx.load("entity._secret = 1").exec().is_err());
+        assert!(ctx.load("entity.mp = 'lots'").exec().is_err());
+
+        // The fallbacks aren't exported as methods
+        assert!(ctx.load("entity:attribute('hp')").exec().is_err());
+
+        // Keys which can't be converted to the fallback's key are an error
+        assert!(ctx.load("local _ = entity[true]").exec().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn index_fallback_any_key() -> rlua::Result<()> {
+    use rudeboy::methods;
+
+    #[metamethods(Index, NewIndex)]
+    #[user_data(MetaMethods, Methods)]
+    struct Switches {
+        pub on: bool,
+        #[lua(skip)]
+        pub flipped: Vec<String>,
+    }
+
+    #[methods]
+    impl Switches {
+        #[lua(index_fallback)]
+        pub fn describe<'lua>(&self, key: rlua::Value<'lua>) -> String {
+            match key {
+                rlua::Value::Boolean(b) => format!("bool {}", b),
+                rlua::Value::Integer(i) => format!("int {}", i),
+                rlua::Value::String(s) => format!("string {}", s.to_str().unwrap_or("?")),
+                _ => "other".to_string(),
+            }
+        }
+
+        #[lua(new_index_fallback)]
+        pub fn flip<'lua>(&mut self, key: rlua::Value<'lua>, value: bool) {
+            if value {
+                self.flipped.push(format!("{:?}", key));
+            }
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("switches", Switches { on: true, flipped: Vec::new() })?;
+
+        assert!(ctx.load("switches.on").eval::<bool>()?);
+        assert_eq!(ctx.load("switches[true]").eval::<String>()?, "bool true");
+        assert_eq!(ctx.load("switches[3]").eval::<String>()?, "int 3");
+        assert_eq!(ctx.load("switches.off").eval::<String>()?, "string off");
+        assert_eq!(ctx.load("switches[{}]").eval::<String>()?, "other");
+
+        ctx.load("switches[false] = true; switches.on = false").exec()?;
+        assert!(!ctx.load("switches.on").eval::<bool>()?);
+        let switches = globals.get::<_, rlua::AnyUserData>("switches")?;
+        assert_eq!(switches.borrow::<Switches>()?.flipped, vec!["Boolean(false)".to_string()]);
+
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn no_such_index() -> rlua::Result<()> {
+    #[metamethods(Index)]
+    #[user_data(MetaMethods)]
+    struct Foo {
+        pub x: i32,
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        ctx.globals().set("foo", Foo { x: 1 })?;
+
+        let error = |code: &str| ctx.load(code).exec().unwrap_err().to_string();
+        assert!(error("local _ = foo.y").contains("No such index: y"));
+        assert!(error("local _ = foo[true]").contains("No such index: true"));
+        assert!(error("local _ = foo[{}]").contains("No such index: <table>"));
+
+        Ok(())
+    })?;
+    Ok(())
+}
@@ -226,3 +226,276 @@ fn multi_mix() -> rlua::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn context() -> rlua::Result<()> {
+    use rlua::Context;
+
+    mod game {
+        use rudeboy::user_data;
+
+        #[user_data]
+        #[derive(Clone)]
+        pub struct Context {
+            pub level: u8,
+        }
+    }
+
+    #[user_data(Methods)]
+    struct Foo {
+        pub bar: u8,
+    }
+
+    #[methods]
+    impl Foo {
+        pub fn to_table<'lua>(&self, ctx: rlua::Context<'lua>) -> rlua::Result<rlua::Table<'lua>> {
+            let table = ctx.create_table()?;
+            table.set("bar", self.bar)?;
+            Ok(table)
+        }
+
+        pub fn globals<'lua>(&self, ctx: Context<'lua>) -> rlua::Table<'lua> {
+            ctx.globals()
+        }
+
+        // Only rlua's Context is passed the calling context
+        pub fn level(&self, context: game::Context) -> u8 {
+            context.level
+        }
+
+        #[lua(raise)]
+        pub fn set_global(&mut self, name: String, ctx: rlua::Context, value: u8) -> rlua::Result<()> {
+            self.bar = value;
+            ctx.globals().set(name, value)
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("foo", Foo { bar: 23 })?;
+
+        let bar = ctx.load("foo:to_table().bar").eval::<u8>()?;
+        assert_eq!(bar, 23);
+
+        ctx.load(r#"foo:set_global("baz", 7)"#).exec()?;
+        assert_eq!(globals.get::<_, u8>("baz")?, 7);
+        assert_eq!(ctx.load("foo:to_table().bar").eval::<u8>()?, 7);
+        assert!(ctx.load("foo:globals() == _G").eval::<bool>()?);
+
+        globals.set("game", game::Context { level: 3 })?;
+        assert_eq!(ctx.load("foo:level(game)").eval::<u8>()?, 3);
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn result() -> rlua::Result<()> {
+    #[user_data(Methods)]
+    struct Foo {
+        pub bar: u8,
+    }
+
+    #[methods]
+    impl Foo {
+        pub fn checked_add(&self, n: u8) -> Result<u8, String> {
+            self.bar.checked_add(n).ok_or_else(|| "overflow".to_string())
+        }
+
+        #[lua(raise)]
+        pub fn add(&mut self, n: u8) -> rlua::Result<()> {
+            self.bar = self.bar.checked_add(n).ok_or_else(|| rlua::Error::RuntimeError("overflow".to_string()))?;
+            Ok(())
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("foo", Foo { bar: 200 })?;
+
+        assert_eq!(ctx.load("foo:checked_add(50)").eval::<u8>()?, 250);
+        let (value, err) = ctx.load("foo:checked_add(60)").eval::<(Option<u8>, String)>()?;
+        assert_eq!((value, err.as_str()), (None, "overflow"));
+
+        ctx.load("foo:add(50)").exec()?;
+        assert!(ctx.load("foo:add(10)").exec().is_err());
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn chain() -> rlua::Result<()> {
+    #[user_data(Methods)]
+    #[derive(Default)]
+    struct Builder {
+        pub width: u32,
+        pub height: u32,
+        pub name: String,
+    }
+
+    #[methods]
+    impl Builder {
+        pub fn width(&mut self, width: u32) -> &mut Self {
+            self.width = width;
+            self
+        }
+
+        pub fn height(&mut self, height: u32) -> &mut Self {
+            self.height = height;
+            self
+        }
+
+        #[lua(chain)]
+        pub fn name(&mut self, name: String) -> Result<(), rlua::Error> {
+            if name.is_empty() {
+                return Err(rlua::Error::RuntimeError("empty name".to_string()));
+            }
+            self.name = name;
+            Ok(())
+        }
+
+        pub fn area(&self) -> u32 {
+            self.width * self.height
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("b", Builder::default())?;
+
+        let area = ctx.load("b:width(3):height(4):name('box'):area()").eval::<u32>()?;
+        assert_eq!(area, 12);
+        assert!(ctx.load("b:width(5) == b").eval::<bool>()?);
+        assert!(ctx.load("b:name('')").exec().is_err());
+
+        let b = globals.get::<_, rlua::AnyUserData>("b")?;
+        let b = b.borrow::<Builder>()?;
+        assert_eq!((b.width, b.height, b.name.as_str()), (5, 4, "box"));
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn consume() -> rlua::Result<()> {
+    use rudeboy::metamethods;
+
+    #[metamethods(Index)]
+    #[user_data(Methods, MetaMethods)]
+    #[derive(Default)]
+    struct Parts {
+        pub head: String,
+        pub tail: Vec<u8>,
+    }
+
+    #[methods]
+    impl Parts {
+        pub fn into_head(self) -> String {
+            self.head
+        }
+
+        pub fn len(&self) -> usize {
+            self.tail.len()
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set(
+            "parts",
+            Parts {
+                head: "head".to_string(),
+                tail: vec![1, 2, 3],
+            },
+        )?;
+
+        assert_eq!(ctx.load("parts:len()").eval::<usize>()?, 3);
+        assert_eq!(ctx.load("parts:into_head()").eval::<String>()?, "head");
+
+        let consumed = |res: rlua::Result<()>| match res {
+            Err(rlua::Error::CallbackError { cause, .. }) => {
+                matches!(&*cause, rlua::Error::RuntimeError(msg) if msg == "already consumed")
+            }
+            _ => false,
+        };
+        assert!(consumed(ctx.load("parts:into_head()").exec()));
+        assert!(consumed(ctx.load("parts:len()").exec()));
+        assert!(consumed(ctx.load("local head = parts.head").exec()));
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn meta() -> rlua::Result<()> {
+    use rudeboy::metamethods;
+
+    #[metamethods(Add, Index)]
+    #[user_data(Methods, MetaMethods)]
+    #[derive(Clone)]
+    struct Path {
+        pub parts: Vec<String>,
+    }
+
+    impl std::ops::Add for Path {
+        type Output = Self;
+
+        fn add(mut self, other: Self) -> Self {
+            self.parts.extend(other.parts);
+            self
+        }
+    }
+
+    #[methods]
+    impl Path {
+        #[meta(Add)]
+        pub fn join(&self, part: String) -> Path {
+            let mut parts = self.parts.clone();
+            parts.push(part);
+            Path { parts }
+        }
+
+        #[meta(ToString)]
+        pub fn display(&self) -> String {
+            self.parts.join("/")
+        }
+
+        #[meta(Len)]
+        pub fn depth(&self) -> usize {
+            self.parts.len()
+        }
+
+        pub fn display_upper(&self) -> String {
+            self.display().to_uppercase()
+        }
+    }
+
+    let lua = Lua::new();
+    lua.context(|ctx| {
+        let globals = ctx.globals();
+        globals.set("path", Path { parts: vec!["usr".to_string()] })?;
+
+        // The tagged method replaces the Add generated by #[metamethods]
+        assert_eq!(ctx.load("tostring(path + 'lib')").eval::<String>()?, "usr/lib");
+        assert_eq!(ctx.load("#(path + 'lib' + 'rudeboy')").eval::<usize>()?, 3);
+        assert_eq!(ctx.load("path:display_upper()").eval::<String>()?, "USR");
+        assert_eq!(ctx.load("path.parts[1]").eval::<String>()?, "usr");
+        assert!(ctx.load("path:display()").exec().is_err());
+
+        Ok(())
+    })?;
+    Ok(())
+}